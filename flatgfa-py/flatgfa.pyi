@@ -24,6 +24,9 @@ class StepList:
 class Path:
     id: int
     name: bytes
+    sample: Optional[str]
+    haplotype: Optional[str]
+    contig: Optional[str]
 
     def __iter__(self) -> Iterator[Handle]: ...
     @overload
@@ -77,6 +80,28 @@ class GAFLine:
 class GAFParser:
     def __iter__(self) -> Iterator[GAFLine]: ...
 
+class PathMatrix:
+    shape: tuple[int, int]
+    indptr: bytes
+    indices: bytes
+    data: bytes
+    def dense(self) -> bytes: ...
+    def to_list(self) -> list[list[int]]: ...
+
+class Similarity:
+    sizes: list[int]
+    def intersection(self, i: int, j: int) -> int: ...
+    def jaccard(self, i: int, j: int) -> float: ...
+    def containment(self, i: int, j: int) -> float: ...
+    def distances(self) -> bytes: ...
+    def __len__(self) -> int: ...
+
+class KmerIndex:
+    k: int
+    def lookup(self, seq: str) -> list[tuple[int, Handle, int]]: ...
+    def __contains__(self, kmer: str) -> bool: ...
+    def __len__(self) -> int: ...
+
 class FlatGFA:
     segments: SegmentList
     paths: PathList
@@ -87,6 +112,9 @@ class FlatGFA:
     def all_reads(self, gaf: str) -> GAFParser: ...
     def print_gaf_lookup(self, gaf: str) -> None: ...
     def make_pangenotype_matrix(self, gaf_files: list[str]) -> list[list[bool]]: ...
+    def path_matrix(self) -> PathMatrix: ...
+    def path_similarity(self) -> Similarity: ...
+    def kmer_index(self, k: int | None = None) -> KmerIndex: ...
 
 def parse(filename: str) -> FlatGFA: ...
 def load(filename: str) -> FlatGFA: ...
@@ -1,10 +1,11 @@
 use flatgfa::namemap::NameMap;
 use flatgfa::ops::gaf::{ChunkEvent, GAFParser};
-use flatgfa::ops::pangenotype;
+use flatgfa::ops::kmer::{self, KmerIndex};
+use flatgfa::ops::{pangenotype, path_matrix, similarity};
 use flatgfa::pool::Id;
 use flatgfa::{self, file, memfile, print, FlatGFA, Handle, HeapGFAStore};
 use memmap::Mmap;
-use pyo3::exceptions::PyIndexError;
+use pyo3::exceptions::{PyIndexError, PyValueError};
 use pyo3::prelude::*;
 use pyo3::types::{PyBytes, PySlice};
 use pyo3::BoundObject;
@@ -51,6 +52,14 @@ impl Store {
             Store::File(ref mmap) => file::view(mmap),
         }
     }
+
+    /// Get the k-mer index saved in a FlatGFA file, if there is one.
+    fn saved_kmers(&self) -> Option<KmerIndex<'_>> {
+        match self {
+            Store::Heap(_) => None,
+            Store::File(ref mmap) => KmerIndex::saved(mmap),
+        }
+    }
 }
 
 /// An efficient representation of a Graphical Fragment Assembly (GFA) file.
@@ -173,6 +182,234 @@ impl PyFlatGFA {
         let gfa = self.0.view();
         pangenotype::make_pangenotype_matrix(&gfa, gaf_files)
     }
+
+    /// Compute the path-by-segment coverage matrix, as a :class:`PathMatrix`.
+    ///
+    /// Row `i` corresponds to ``graph.paths[i]`` and column `j` to
+    /// ``graph.segments[j]``. Each entry counts the path's traversals of the segment.
+    fn path_matrix(&self) -> PyPathMatrix {
+        let gfa = self.0.view();
+        PyPathMatrix(path_matrix::path_matrix(&gfa, gfa.paths.ids()))
+    }
+
+    /// Compute the pairwise similarity between paths, as a :class:`Similarity`.
+    ///
+    /// Similarities are based on the distinct segments that each path
+    /// traverses, weighted by sequence length.
+    fn path_similarity(&self) -> PySimilarity {
+        let gfa = self.0.view();
+        let sets: Vec<_> = gfa.paths.ids().map(|id| vec![id]).collect();
+        PySimilarity(similarity::similarity(&gfa, &sets))
+    }
+
+    /// Get an index of the graph's `k`-mers, as a :class:`KmerIndex`.
+    ///
+    /// If the graph was loaded from a FlatGFA file with a saved index (see
+    /// ``fgfa kmers --save``), and `k` is omitted or matches, this uses the saved
+    /// index. Otherwise, it builds a new one with `k` (default 21, at most 31).
+    #[pyo3(signature = (k=None))]
+    fn kmer_index(&self, k: Option<usize>) -> PyResult<PyKmerIndex> {
+        let saved = self.0.saved_kmers().map(|index| index.k);
+        if saved.is_some() && (k.is_none() || k == saved) {
+            return Ok(PyKmerIndex {
+                store: self.0.clone(),
+                built: None,
+            });
+        }
+        let k = k.unwrap_or(21);
+        if k == 0 || k > kmer::MAX_K {
+            return Err(PyValueError::new_err("k must be between 1 and 31"));
+        }
+        Ok(PyKmerIndex {
+            store: self.0.clone(),
+            built: Some(KmerIndex::build(&self.0.view(), k)),
+        })
+    }
+}
+
+/// A path-by-segment coverage matrix, stored in compressed sparse row (CSR) form.
+///
+/// The `indptr`, `indices`, and `data` arrays are exposed as bytes objects in
+/// native byte order, which support the buffer protocol. So you can build a
+/// SciPy sparse matrix without copying through Python lists::
+///
+///     m = graph.path_matrix()
+///     csr = scipy.sparse.csr_matrix((
+///         numpy.frombuffer(m.data, dtype=numpy.uint32),
+///         numpy.frombuffer(m.indices, dtype=numpy.uint32),
+///         numpy.frombuffer(m.indptr, dtype=numpy.uint64),
+///     ), shape=m.shape)
+///
+/// Or use :meth:`PathMatrix.dense` for a dense row-major array.
+#[pyclass(frozen)]
+#[pyo3(name = "PathMatrix", module = "flatgfa")]
+struct PyPathMatrix(path_matrix::PathMatrix);
+
+#[pymethods]
+impl PyPathMatrix {
+    /// The matrix dimensions, as a (paths, segments) tuple.
+    #[getter]
+    fn shape(&self) -> (usize, usize) {
+        (self.0.rows(), self.0.cols)
+    }
+
+    /// The CSR row pointers, as a buffer of `uint64` values.
+    #[getter]
+    fn indptr<'py>(&self, py: Python<'py>) -> Bound<'py, PyBytes> {
+        let bytes: Vec<u8> = self.0.indptr.iter().flat_map(|x| x.to_ne_bytes()).collect();
+        PyBytes::new(py, &bytes)
+    }
+
+    /// The CSR column indices (segment IDs), as a buffer of `uint32` values.
+    #[getter]
+    fn indices<'py>(&self, py: Python<'py>) -> Bound<'py, PyBytes> {
+        let bytes: Vec<u8> = self
+            .0
+            .indices
+            .iter()
+            .flat_map(|x| x.to_ne_bytes())
+            .collect();
+        PyBytes::new(py, &bytes)
+    }
+
+    /// The CSR counts, as a buffer of `uint32` values.
+    #[getter]
+    fn data<'py>(&self, py: Python<'py>) -> Bound<'py, PyBytes> {
+        let bytes: Vec<u8> = self.0.data.iter().flat_map(|x| x.to_ne_bytes()).collect();
+        PyBytes::new(py, &bytes)
+    }
+
+    /// Get the entire matrix as a dense, row-major buffer of `uint32` values.
+    fn dense<'py>(&self, py: Python<'py>) -> Bound<'py, PyBytes> {
+        let bytes: Vec<u8> = (0..self.0.rows())
+            .flat_map(|row| self.0.dense_row(row))
+            .flat_map(|x| x.to_ne_bytes())
+            .collect();
+        PyBytes::new(py, &bytes)
+    }
+
+    /// Get the matrix as a list of lists of counts.
+    fn to_list(&self) -> Vec<Vec<u32>> {
+        (0..self.0.rows())
+            .map(|row| self.0.dense_row(row))
+            .collect()
+    }
+}
+
+/// Pairwise similarities between paths, indexed like ``graph.paths``.
+///
+/// :meth:`Similarity.distances` gives the Jaccard distances as a condensed
+/// matrix, which you can pass straight to SciPy for clustering::
+///
+///     d = numpy.frombuffer(graph.path_similarity().distances())
+///     tree = scipy.cluster.hierarchy.linkage(d)
+#[pyclass(frozen)]
+#[pyo3(name = "Similarity", module = "flatgfa")]
+struct PySimilarity(similarity::Similarity);
+
+#[pymethods]
+impl PySimilarity {
+    /// The total length of the distinct segments in each path.
+    #[getter]
+    fn sizes(&self) -> Vec<usize> {
+        self.0.sizes.clone()
+    }
+
+    /// The length of the segments that paths `i` and `j` share.
+    fn intersection(&self, i: usize, j: usize) -> usize {
+        self.0.intersection(i, j)
+    }
+
+    /// The Jaccard similarity between paths `i` and `j`.
+    fn jaccard(&self, i: usize, j: usize) -> f64 {
+        self.0.jaccard(i, j)
+    }
+
+    /// The fraction of path `i`'s sequence that is also in path `j`.
+    fn containment(&self, i: usize, j: usize) -> f64 {
+        self.0.containment(i, j)
+    }
+
+    /// The Jaccard distances in condensed form, as a buffer of `float64` values.
+    fn distances<'py>(&self, py: Python<'py>) -> Bound<'py, PyBytes> {
+        let bytes: Vec<u8> = self
+            .0
+            .condensed_distances()
+            .iter()
+            .flat_map(|x| x.to_ne_bytes())
+            .collect();
+        PyBytes::new(py, &bytes)
+    }
+
+    fn __len__(&self) -> usize {
+        self.0.len()
+    }
+}
+
+/// An index from k-mers to the places they occur in a graph.
+///
+/// The index includes k-mers that span segment boundaries, and it covers both
+/// orientations of every segment, so looking up a sequence also finds its
+/// reverse complement.
+#[pyclass(frozen)]
+#[pyo3(name = "KmerIndex", module = "flatgfa")]
+struct PyKmerIndex {
+    store: Arc<Store>,
+
+    /// A freshly built index, or `None` to use the one saved in the file.
+    built: Option<KmerIndex<'static>>,
+}
+
+impl PyKmerIndex {
+    fn index(&self) -> KmerIndex<'_> {
+        match &self.built {
+            Some(index) => KmerIndex {
+                k: index.k,
+                entries: std::borrow::Cow::Borrowed(&index.entries),
+            },
+            None => self.store.saved_kmers().unwrap(),
+        }
+    }
+}
+
+#[pymethods]
+impl PyKmerIndex {
+    /// The k-mer length.
+    #[getter]
+    fn k(&self) -> usize {
+        self.index().k
+    }
+
+    /// Find every k-mer in `seq`. Returns a list of `(offset, handle, handle_offset)`
+    /// tuples: a k-mer starting at `offset` in `seq` also starts at `handle_offset`
+    /// in the (oriented) sequence for :class:`Handle` `handle`.
+    fn lookup(&self, seq: &str) -> Vec<(usize, PyHandle, u32)> {
+        let index = self.index();
+        index
+            .query(seq.as_bytes())
+            .flat_map(|(offset, hits)| {
+                hits.iter().map(move |hit| {
+                    let handle = PyHandle {
+                        store: self.store.clone(),
+                        handle: hit.handle,
+                    };
+                    (offset, handle, hit.offset)
+                })
+            })
+            .collect()
+    }
+
+    /// Check whether a single k-mer occurs anywhere in the graph.
+    fn __contains__(&self, kmer: &str) -> bool {
+        let index = self.index();
+        kmer.len() == index.k
+            && kmer::encode(kmer.as_bytes()).is_some_and(|code| !index.lookup(code).is_empty())
+    }
+
+    /// The number of k-mer positions in the index.
+    fn __len__(&self) -> usize {
+        self.index().entries.len()
+    }
 }
 
 /// A reference to a list of *any* type within a FlatGFA.
@@ -459,6 +696,30 @@ impl PyPath {
         name.try_into().unwrap()
     }
 
+    /// The sample name, if the path name follows PanSN (``sample#hap#contig``).
+    #[getter]
+    fn sample(&self) -> Option<String> {
+        let gfa = self.0.store.view();
+        let pansn = gfa.get_path_pansn(&gfa.paths[self.0.id()])?;
+        Some(pansn.sample.to_string())
+    }
+
+    /// The haplotype, if the path name follows PanSN (``sample#hap#contig``).
+    #[getter]
+    fn haplotype(&self) -> Option<String> {
+        let gfa = self.0.store.view();
+        let pansn = gfa.get_path_pansn(&gfa.paths[self.0.id()])?;
+        pansn.haplotype.map(|h| h.to_string())
+    }
+
+    /// The contig name, if the path name follows PanSN (``sample#hap#contig``).
+    #[getter]
+    fn contig(&self) -> Option<String> {
+        let gfa = self.0.store.view();
+        let pansn = gfa.get_path_pansn(&gfa.paths[self.0.id()])?;
+        Some(pansn.contig.to_string())
+    }
+
     fn __repr__(&self) -> String {
         self.0.py_repr("Path")
     }
@@ -914,5 +1175,8 @@ fn pymod(m: &Bound<'_, PyModule>) -> PyResult<()> {
     m.add_class::<LinkList>()?;
     m.add_class::<StepList>()?;
     m.add_class::<PyChunkEvent>()?;
+    m.add_class::<PyPathMatrix>()?;
+    m.add_class::<PySimilarity>()?;
+    m.add_class::<PyKmerIndex>()?;
     Ok(())
 }
@@ -56,6 +56,16 @@ def test_paths_find(gfa):
     assert path.name == "two"
 
 
+def test_path_pansn():
+    gfa = flatgfa.parse_bytes(
+        b"S\t1\tA\nP\tHG002#1#chr20\t1+\t*\nP\tgrch38#chr20\t1+\t*\nP\tx\t1+\t*\n"
+    )
+    pansn, no_hap, plain = gfa.paths
+    assert (pansn.sample, pansn.haplotype, pansn.contig) == ("HG002", "1", "chr20")
+    assert (no_hap.sample, no_hap.haplotype, no_hap.contig) == ("grch38", None, "chr20")
+    assert (plain.sample, plain.haplotype, plain.contig) == (None, None, None)
+
+
 def test_path_steps(gfa):
     # When you get a path, the path itself acts as a list of steps (handles).
     path = gfa.paths[1]
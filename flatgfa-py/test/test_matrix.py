@@ -15,3 +15,48 @@ def test_pangenotype_matrix():
         [True, True, True, True],
         [True, True, False, True],
     ]
+
+
+def test_path_matrix():
+    gfa = flatgfa.parse_bytes(TEST_GFA.read_bytes())
+    matrix = gfa.path_matrix()
+    assert matrix.shape == (2, 4)
+    assert matrix.to_list() == [
+        [1, 1, 0, 1],
+        [1, 1, 1, 1],
+    ]
+
+    # The CSR arrays are raw buffers of native-endian integers.
+    indptr = memoryview(matrix.indptr).cast("Q")
+    assert list(indptr) == [0, 3, 7]
+    indices = memoryview(matrix.indices).cast("I")
+    assert list(indices) == [0, 1, 3, 0, 1, 2, 3]
+    assert list(memoryview(matrix.dense()).cast("I")) == [1, 1, 0, 1, 1, 1, 1, 1]
+
+
+def test_path_similarity():
+    gfa = flatgfa.parse_bytes(TEST_GFA.read_bytes())
+    sim = gfa.path_similarity()
+    assert len(sim) == 2
+    assert sim.sizes == [38, 41]
+    assert sim.intersection(0, 1) == 38
+    assert sim.jaccard(0, 1) == 38 / 41
+    assert sim.containment(0, 1) == 1.0
+    assert list(memoryview(sim.distances()).cast("d")) == [1 - 38 / 41]
+
+
+def test_kmer_index():
+    gfa = flatgfa.parse_bytes(TEST_GFA.read_bytes())
+    index = gfa.kmer_index(5)
+    assert index.k == 5
+    assert "CAAAT" in index
+    assert "ATTTG" in index  # The reverse complement.
+    assert "GGGGG" not in index
+
+    # TAAGA spans segments 1 and 2.
+    hits = index.lookup("TAAGA")
+    assert len(hits) == 1
+    offset, handle, handle_offset = hits[0]
+    assert offset == 0
+    assert handle.segment.name == 1 and handle.is_forward
+    assert handle_offset == 4
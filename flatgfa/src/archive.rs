@@ -0,0 +1,186 @@
+//! A container file that holds several FlatGFA graphs, each with a name.
+//!
+//! An archive starts with a header and a directory of entries, followed by the
+//! graph names and then the graphs themselves. Each graph is stored exactly as
+//! it would be in its own FlatGFA file, so `file::view` works on its bytes.
+//! Tools address a single graph in an archive as `archive.flatgfa#name`.
+
+use crate::file;
+use crate::flatgfa::FlatGFA;
+use bstr::BStr;
+use std::io::Read;
+use std::mem::size_of;
+use std::path::Path;
+use zerocopy::{FromBytes, Immutable, IntoBytes, KnownLayout};
+
+const MAGIC_NUMBER: u64 = 0xB101_1A2C;
+
+/// Graphs start at offsets that are multiples of this.
+const ALIGN: usize = 8;
+
+#[derive(FromBytes, IntoBytes, Immutable, KnownLayout, Debug)]
+#[repr(C)]
+struct Header {
+    magic: u64,
+    count: u64,
+}
+
+/// A directory entry: the byte ranges of a graph's name and its data.
+#[derive(FromBytes, IntoBytes, Immutable, KnownLayout, Debug, Clone, Copy)]
+#[repr(C)]
+struct Entry {
+    name_start: u64,
+    name_len: u64,
+    offset: u64,
+    size: u64,
+}
+
+/// Lay out an archive, producing its directory and total size.
+fn layout(graphs: &[(&[u8], FlatGFA)]) -> (Vec<Entry>, usize) {
+    let mut pos = size_of::<Header>() + graphs.len() * size_of::<Entry>();
+    let mut entries = Vec::with_capacity(graphs.len());
+    for (name, _) in graphs {
+        entries.push(Entry {
+            name_start: pos as u64,
+            name_len: name.len() as u64,
+            offset: 0,
+            size: 0,
+        });
+        pos += name.len();
+    }
+    for (entry, (_, gfa)) in entries.iter_mut().zip(graphs) {
+        pos = pos.next_multiple_of(ALIGN);
+        entry.offset = pos as u64;
+        entry.size = file::size(gfa) as u64;
+        pos += entry.size as usize;
+    }
+    (entries, pos)
+}
+
+/// Get the total size in bytes of an archive containing some named graphs.
+pub fn size(graphs: &[(&[u8], FlatGFA)]) -> usize {
+    layout(graphs).1
+}
+
+/// Write named graphs into an archive in a byte buffer, which must be exactly
+/// `size(graphs)` bytes long.
+pub fn dump(graphs: &[(&[u8], FlatGFA)], buf: &mut [u8]) {
+    let (entries, total) = layout(graphs);
+    assert_eq!(buf.len(), total);
+
+    let header = Header {
+        magic: MAGIC_NUMBER,
+        count: graphs.len() as u64,
+    };
+    let rest = file::write_bump(buf, &header).unwrap();
+    file::write_bump(rest, entries.as_slice()).unwrap();
+    for (entry, (name, gfa)) in entries.iter().zip(graphs) {
+        let start = entry.name_start as usize;
+        buf[start..start + name.len()].copy_from_slice(name);
+        let offset = entry.offset as usize;
+        file::dump(gfa, &mut buf[offset..offset + entry.size as usize]);
+    }
+}
+
+/// Check whether a buffer contains an archive (rather than a single graph).
+pub fn is_archive(data: &[u8]) -> bool {
+    Header::read_from_prefix(data).is_ok_and(|(header, _)| header.magic == MAGIC_NUMBER)
+}
+
+/// List the names of the graphs in an archive and the bytes of each one.
+pub fn entries(data: &[u8]) -> Result<Vec<(&BStr, &[u8])>, &'static str> {
+    let (header, mut rest) = Header::read_from_prefix(data).map_err(|_| "archive too short")?;
+    if header.magic != MAGIC_NUMBER {
+        return Err("not a FlatGFA archive");
+    }
+    let mut out = Vec::with_capacity(header.count as usize);
+    for _ in 0..header.count {
+        let (entry, next) = Entry::read_from_prefix(rest).map_err(|_| "archive truncated")?;
+        rest = next;
+        let name = data
+            .get(entry.name_start as usize..(entry.name_start + entry.name_len) as usize)
+            .ok_or("archive truncated")?;
+        let graph = data
+            .get(entry.offset as usize..(entry.offset + entry.size) as usize)
+            .ok_or("archive truncated")?;
+        out.push((name.into(), graph));
+    }
+    Ok(out)
+}
+
+/// Check whether a file starts with an archive header.
+fn is_archive_file(name: &str) -> bool {
+    let mut buf = [0; size_of::<Header>()];
+    std::fs::File::open(name)
+        .and_then(|mut file| file.read_exact(&mut buf))
+        .is_ok()
+        && is_archive(&buf)
+}
+
+/// Split a filename like `archive.flatgfa#name` into the file and the graph name.
+///
+/// Ordinary filenames can contain `#` too, so the suffix only names a graph if
+/// there is no file with the whole name or if the part before the `#` is an
+/// archive.
+pub fn split_name(name: &str) -> (&str, Option<&str>) {
+    match name.rsplit_once('#') {
+        Some((file, graph)) if !Path::new(name).exists() || is_archive_file(file) => {
+            (file, Some(graph))
+        }
+        _ => (name, None),
+    }
+}
+
+/// Get a FlatGFA backed by a byte buffer that contains either a single graph or,
+/// if `graph` names one, an archive.
+pub fn view<'a>(data: &'a [u8], graph: Option<&str>) -> Result<FlatGFA<'a>, &'static str> {
+    match graph {
+        Some(graph) => {
+            let (_, bytes) = entries(data)?
+                .into_iter()
+                .find(|(name, _)| *name == graph)
+                .ok_or("graph not found in archive")?;
+            Ok(file::view(bytes))
+        }
+        None if is_archive(data) => Err("specify a graph in the archive as `file#name`"),
+        None => Ok(file::view(data)),
+    }
+}
+
+#[test]
+fn test_archive() {
+    let a = crate::parse::Parser::for_heap().parse_mem(b"S\t1\tACGT\nP\tx\t1+\t*\n");
+    let b = crate::parse::Parser::for_heap().parse_mem(b"S\t1\tA\nS\t2\tC\nL\t1\t+\t2\t+\t0M\n");
+    let graphs: [(&[u8], FlatGFA); 2] = [(b"abc", a.as_ref()), (b"d", b.as_ref())];
+
+    let mut buf = vec![0; size(&graphs)];
+    dump(&graphs, &mut buf);
+    assert!(is_archive(&buf));
+    let names: Vec<_> = entries(&buf).unwrap().iter().map(|(n, _)| *n).collect();
+    assert_eq!(names, vec!["abc", "d"]);
+
+    let d = view(&buf, Some("d")).unwrap();
+    assert_eq!(d.segs.len(), 2);
+    assert_eq!(d.links.len(), 1);
+    assert_eq!(view(&buf, Some("abc")).unwrap().paths.len(), 1);
+    assert!(view(&buf, Some("e")).is_err());
+    assert!(view(&buf, None).is_err());
+
+    assert_eq!(split_name("x.flatgfa#abc"), ("x.flatgfa", Some("abc")));
+    assert_eq!(split_name("x.flatgfa"), ("x.flatgfa", None));
+
+    // A file whose name contains a `#` is not an archive reference.
+    let dir = std::env::temp_dir().join(format!("flatgfa-archive-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let plain = dir.join("h#1.flatgfa");
+    std::fs::write(&plain, b"").unwrap();
+    let plain = plain.to_str().unwrap();
+    assert_eq!(split_name(plain), (plain, None));
+
+    // But it is if the part before the `#` is an archive.
+    let arch = dir.join("h");
+    std::fs::write(&arch, &buf).unwrap();
+    let arch = arch.to_str().unwrap();
+    assert_eq!(split_name(plain), (arch, Some("1.flatgfa")));
+    std::fs::remove_dir_all(&dir).unwrap();
+}
@@ -1,16 +1,21 @@
+use crate::archive;
 use crate::emit::Emit;
-use crate::flatbed::BEDParser;
+use crate::file;
+use crate::flatbed::{BEDParser, HeapBEDStore};
 use crate::flatgfa::{self, Segment};
 use crate::memfile::{self, map_file};
 use crate::namemap::NameMap;
+use crate::ops::fasta::PathSelection;
+use crate::ops::position::PathRange;
 use crate::packedseq::PackedSeqView;
+use crate::pansn::{GroupBy, PathGroups};
 use crate::pool::Id;
 use crate::{ops, packedseq};
 use argh::FromArgs;
 use bstr::{BStr, BString};
 use rayon::iter::ParallelIterator;
-use std::collections::HashMap;
-use std::io::Write;
+use std::collections::{HashMap, HashSet};
+use std::io::{Seek, Write};
 
 /// print the FlatGFA table of contents
 #[derive(FromArgs, PartialEq, Debug)]
@@ -54,12 +59,47 @@ pub fn toc(gfa: &flatgfa::FlatGFA, args: Toc) {
 /// list the paths
 #[derive(FromArgs, PartialEq, Debug)]
 #[argh(subcommand, name = "paths")]
-pub struct Paths {}
+pub struct Paths {
+    /// print path sequences as FASTA instead of listing names
+    #[argh(switch, short = 'f')]
+    fasta: bool,
 
-pub fn paths(gfa: &flatgfa::FlatGFA) {
-    for path in gfa.paths.all().iter() {
-        println!("{}", gfa.get_path_name(path));
+    /// in FASTA mode, only print this path (or `path:start-end` range)
+    #[argh(option, short = 'p')]
+    path: Vec<PathRange>,
+
+    /// in FASTA mode, wrap sequence lines at this width (0 for no wrapping)
+    #[argh(option, short = 'w', default = "80")]
+    width: usize,
+
+    /// list the samples (from PanSN path names) instead of the paths
+    #[argh(switch, long = "by-sample")]
+    by_sample: bool,
+}
+
+pub fn paths(gfa: &flatgfa::FlatGFA, args: Paths) -> Result<(), &'static str> {
+    if args.by_sample {
+        if args.fasta {
+            return Err("--by-sample does not apply to FASTA output");
+        }
+        for name in PathGroups::new(gfa, GroupBy::Sample).names {
+            println!("{name}");
+        }
+    } else if args.fasta {
+        let selections = if args.path.is_empty() {
+            PathSelection::all(gfa)
+        } else {
+            PathSelection::find(gfa, &args.path)?
+        };
+        let stdout = std::io::stdout();
+        let mut out = std::io::BufWriter::new(stdout.lock());
+        ops::fasta::paths_to_fasta(gfa, &selections, args.width, &mut out).unwrap();
+    } else {
+        for path in gfa.paths.all().iter() {
+            println!("{}", gfa.get_path_name(path));
+        }
     }
+    Ok(())
 }
 
 /// calculate graph statistics
@@ -73,9 +113,16 @@ pub struct Stats {
     /// number of segments with at least one self-loop link
     #[argh(switch, short = 'L')]
     self_loops: bool,
+
+    /// write the full summary as JSON instead of text
+    #[argh(switch, short = 'j')]
+    json: bool,
 }
 
-pub fn stats(gfa: &flatgfa::FlatGFA, args: Stats) {
+pub fn stats(gfa: &flatgfa::FlatGFA, args: Stats) -> Result<(), &'static str> {
+    if args.json && (args.summarize || args.self_loops) {
+        return Err("-j applies only to the full summary");
+    }
     if args.summarize {
         println!("#length\tnodes\tedges\tpaths\tsteps");
         println!(
@@ -99,7 +146,12 @@ pub fn stats(gfa: &flatgfa::FlatGFA, args: Stats) {
         println!("#type\tnum");
         println!("total\t{total}");
         println!("unique\t{}", counts.len());
+    } else if args.json {
+        ops::stats::StatsJson(ops::stats::Stats::new(gfa)).print();
+    } else {
+        ops::stats::Stats::new(gfa).print();
     }
+    Ok(())
 }
 
 /// find a nucleotide position within a path
@@ -175,43 +227,142 @@ pub fn bench(args: Bench) {
 #[derive(FromArgs, PartialEq, Debug)]
 #[argh(subcommand, name = "extract")]
 pub struct Extract {
-    /// segment to extract around
+    /// segment to extract around (may be repeated with --context-bases)
     #[argh(option, short = 'n')]
-    seg_name: usize,
+    seg_name: Vec<usize>,
+
+    /// extract around a `path:start-end` range
+    #[argh(option, short = 'r')]
+    range: Vec<PathRange>,
+
+    /// extract around the path ranges in a BED file
+    #[argh(option, short = 'b')]
+    bed: Option<String>,
 
-    /// number of edges "away" from the node to include
+    /// number of edges "away" from the node to include (default: 0)
     #[argh(option, short = 'c')]
-    link_distance: usize,
+    link_distance: Option<usize>,
+
+    /// include segments up to this many base pairs away instead of counting links
+    #[argh(option, short = 'L', long = "context-bases")]
+    context_bases: Option<usize>,
 
     /// maximum number of basepairs allowed between subpaths s.t. the subpaths are merged together
-    #[argh(
-        option,
-        short = 'd',
-        long = "max-distance-subpaths",
-        default = "300000"
-    )]
-    max_distance_subpaths: usize, // TODO: possibly make this bigger
+    /// (default: 300000)
+    #[argh(option, short = 'd', long = "max-distance-subpaths")]
+    max_distance_subpaths: Option<usize>, // TODO: possibly make this bigger
+
+    /// maximum number of iterations before we stop merging subpaths (default: 6)
+    #[argh(option, short = 'e', long = "max-merging-iterations")]
+    num_iterations: Option<usize>, // TODO: probably make this smaller
+
+    /// write each range to its own `<prefix>.<N>.flatgfa` file instead
+    #[argh(option, short = 'p')]
+    prefix: Option<String>,
+
+    /// with --prefix, write GFA text files (`<prefix>.<N>.gfa`)
+    #[argh(switch, short = 'g')]
+    gfa: bool,
+}
 
-    /// maximum number of iterations before we stop merging subpaths
-    #[argh(option, short = 'e', long = "max-merging-iterations", default = "6")]
-    num_iterations: usize, // TODO: probably make this smaller
+/// Write a graph to a FlatGFA file, or to a GFA text file if `text` is set.
+fn write_graph(gfa: &flatgfa::FlatGFA, name: &str, text: bool) -> Result<(), &'static str> {
+    if text {
+        let mut out = std::fs::File::create(name).map_err(|_| "could not create output")?;
+        write!(out, "{}", gfa).map_err(|_| "could not write output")
+    } else {
+        let mut mmap = memfile::map_new_file(name, file::size(gfa) as u64);
+        file::dump(gfa, &mut mmap);
+        mmap.flush().map_err(|_| "could not write output")
+    }
 }
 
+/// Extract a subgraph. Returns `None` when the subgraphs were written to
+/// separate files instead.
 pub fn extract(
     gfa: &flatgfa::FlatGFA,
     args: Extract,
-) -> Result<flatgfa::HeapGFAStore, &'static str> {
-    let origin_seg = gfa.find_seg(args.seg_name).ok_or("segment not found")?;
-
-    let mut subgraph = ops::extract::SubgraphBuilder::new(gfa);
-    subgraph.add_header();
-    subgraph.extract(
-        origin_seg,
-        args.link_distance,
-        args.max_distance_subpaths,
-        args.num_iterations,
-    );
-    Ok(subgraph.store)
+) -> Result<Option<flatgfa::HeapGFAStore>, &'static str> {
+    if !args.seg_name.is_empty() {
+        if !args.range.is_empty() || args.bed.is_some() {
+            return Err("specify either a segment or ranges, not both");
+        }
+        let origins = args
+            .seg_name
+            .iter()
+            .map(|&name| gfa.find_seg(name).ok_or("segment not found"))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let merging = args.max_distance_subpaths.is_some() || args.num_iterations.is_some();
+
+        let mut subgraph = ops::extract::SubgraphBuilder::new(gfa);
+        subgraph.add_header();
+        match (args.context_bases, origins.as_slice()) {
+            (Some(_), _) if args.link_distance.is_some() => {
+                return Err("--context-bases cannot be combined with --link-distance")
+            }
+            (Some(_), _) if merging => {
+                return Err("subpath merging options only work with --link-distance")
+            }
+            (Some(bp), _) => subgraph.extract_bp(&origins, bp),
+            (None, &[origin_seg]) => subgraph.extract(
+                origin_seg,
+                args.link_distance.unwrap_or(0),
+                args.max_distance_subpaths
+                    .unwrap_or(ops::extract::MAX_DISTANCE_SUBPATHS),
+                args.num_iterations
+                    .unwrap_or(ops::extract::MAX_MERGING_ITERATIONS),
+            ),
+            (None, _) => return Err("multiple segments require --context-bases"),
+        }
+        return Ok(Some(subgraph.store));
+    }
+    if args.context_bases.is_some() {
+        return Err("--context-bases only works with segments (-n)");
+    }
+    if args.max_distance_subpaths.is_some() || args.num_iterations.is_some() {
+        return Err("subpath merging options only work with a segment (-n)");
+    }
+
+    // Gather the ranges from both the BED file and the command line.
+    let mut ranges = Vec::new();
+    if let Some(bed) = &args.bed {
+        let file = memfile::map_file(bed);
+        let bed = BEDParser::for_heap().parse_mem(file.as_ref());
+        let bed = bed.as_ref();
+        for entry in bed.entries.all() {
+            let path = gfa
+                .find_path(bed.get_name_of_entry(entry))
+                .ok_or("path not found")?;
+            ranges.push((path, entry.start as usize..entry.end as usize));
+        }
+    }
+    for spec in &args.range {
+        let path = gfa.find_path(spec.name()).ok_or("path not found")?;
+        let len = ops::position::path_len(gfa, &gfa.paths[path]);
+        ranges.push((path, spec.range_or(len)));
+    }
+    if ranges.is_empty() {
+        return Err("specify a segment (-n) or ranges (-r or -b) to extract");
+    }
+
+    let build = |ranges: &[(Id<flatgfa::Path>, std::ops::Range<usize>)]| {
+        let mut subgraph = ops::extract::SubgraphBuilder::new(gfa);
+        subgraph.add_header();
+        subgraph.extract_ranges(ranges, args.link_distance.unwrap_or(0));
+        subgraph.store
+    };
+    match &args.prefix {
+        Some(prefix) => {
+            for (idx, range) in ranges.iter().enumerate() {
+                let store = build(std::slice::from_ref(range));
+                let ext = if args.gfa { "gfa" } else { "flatgfa" };
+                write_graph(&store.as_ref(), &format!("{prefix}.{idx}.{ext}"), args.gfa)?;
+            }
+            Ok(None)
+        }
+        None => Ok(Some(build(&ranges))),
+    }
 }
 
 /// compute depth: the number of times paths cross a node
@@ -229,11 +380,20 @@ pub struct Depth {
     /// show depth for intervals from a BED file
     #[argh(option, long = "bed-input", short = 'b')]
     bed: Option<String>,
+
+    /// in path mode, aggregate paths by sample (from PanSN path names)
+    #[argh(switch, long = "by-sample")]
+    by_sample: bool,
 }
 
-pub fn depth(gfa: &flatgfa::FlatGFA, args: Depth) {
-    use crate::ops::depth::{path_depth, seg_depth_with_uniq, PathDepth, SegDepth};
+pub fn depth(gfa: &flatgfa::FlatGFA, args: Depth) -> Result<(), &'static str> {
+    use crate::ops::depth::{
+        group_depth, path_depth, seg_depth_with_uniq, GroupDepth, PathDepth, SegDepth,
+    };
     use crate::ops::window_depth::{bed_depth, IntervalDepth};
+    if args.by_sample && (args.seg_depth || args.bed.is_some()) {
+        return Err("--by-sample only applies to path depth");
+    }
     if args.seg_depth {
         // Segment depth table.
         let (depths, uniq_depths) = seg_depth_with_uniq(gfa);
@@ -255,7 +415,22 @@ pub fn depth(gfa: &flatgfa::FlatGFA, args: Depth) {
         .print()
     } else {
         // Path depth table.
-        if args.path.is_empty() {
+        if args.by_sample {
+            // Aggregate by sample.
+            let groups = if args.path.is_empty() {
+                PathGroups::new(gfa, GroupBy::Sample)
+            } else {
+                let path_ids = args.path.iter().filter_map(|n| gfa.find_path(n.as_ref()));
+                PathGroups::of_paths(gfa, path_ids, GroupBy::Sample)
+            };
+            let (lengths, depths) = group_depth(gfa, &groups);
+            GroupDepth {
+                groups: &groups,
+                lengths,
+                depths,
+            }
+            .print();
+        } else if args.path.is_empty() {
             // All paths.
             let (lengths, depths) = path_depth(gfa, gfa.paths.ids());
             PathDepth {
@@ -282,6 +457,7 @@ pub fn depth(gfa: &flatgfa::FlatGFA, args: Depth) {
             .print();
         }
     }
+    Ok(())
 }
 
 /// chop the segments in a graph into sizes of N or smaller
@@ -483,14 +659,957 @@ pub struct WindowDepth {
 
     #[argh(positional)]
     window: usize,
+
+    /// count the samples (from PanSN path names) at each segment, not the paths
+    #[argh(switch, long = "by-sample")]
+    by_sample: bool,
 }
 
 pub fn window_depth(gfa: &flatgfa::FlatGFA, args: WindowDepth) {
     let path = gfa.find_path(args.path.as_ref()).expect("path not found");
-    let (intervals, depths) = ops::window_depth::window_depth(gfa, path, args.window);
+    let (intervals, depths) = if args.by_sample {
+        let groups = PathGroups::new(gfa, GroupBy::Sample);
+        let depth = ops::depth::group_seg_depth(gfa, &groups);
+        ops::window_depth::window_depth_with(gfa, &depth, path, args.window)
+    } else {
+        ops::window_depth::window_depth(gfa, path, args.window)
+    };
     ops::window_depth::IntervalDepth {
         intervals: intervals.as_ref(),
         depths,
     }
     .print()
 }
+
+/// find the paths that overlap intervals on other paths
+#[derive(FromArgs, PartialEq, Debug)]
+#[argh(subcommand, name = "overlap")]
+pub struct Overlap {
+    /// query intervals from a BED file
+    #[argh(option, short = 'b')]
+    bed: Option<String>,
+
+    /// query a path (or a `path:start-end` range)
+    #[argh(option, short = 'r')]
+    path: Vec<PathRange>,
+
+    /// print only the overlapping intervals, as BED
+    #[argh(switch, long = "bed-output")]
+    bed_output: bool,
+}
+
+pub fn overlap(gfa: &flatgfa::FlatGFA, args: Overlap) -> Result<(), &'static str> {
+    // Gather the query intervals from both the BED file and the command line.
+    let mut queries = match args.bed {
+        Some(bed) => {
+            let file = memfile::map_file(&bed);
+            BEDParser::for_heap().parse_mem(file.as_ref())
+        }
+        None => HeapBEDStore::default(),
+    };
+    for spec in &args.path {
+        let path_id = gfa.find_path(spec.name()).ok_or("path not found")?;
+        let len = ops::position::path_len(gfa, &gfa.paths[path_id]);
+        let range = spec.range_or(len);
+        queries.add_entry(&spec.name, range.start as u64, range.end as u64);
+    }
+
+    let (hits, query_idxs) = ops::overlap::overlap(gfa, &queries.as_ref())?;
+    if args.bed_output {
+        let hits = hits.as_ref();
+        for entry in hits.entries.all() {
+            let (start, end) = (entry.start, entry.end);
+            println!("{}\t{}\t{}", hits.get_name_of_entry(entry), start, end);
+        }
+    } else {
+        ops::overlap::OverlapTable {
+            queries: queries.as_ref(),
+            hits: hits.as_ref(),
+            query_idxs,
+        }
+        .print();
+    }
+    Ok(())
+}
+
+/// create a graph that contains only some of the paths
+#[derive(FromArgs, PartialEq, Debug)]
+#[argh(subcommand, name = "somepaths")]
+pub struct SomePaths {
+    /// keep the path with this name
+    #[argh(option, short = 'p')]
+    path: Vec<BString>,
+
+    /// keep the paths named in this file, one per line
+    #[argh(option, short = 'f')]
+    file: Option<String>,
+
+    /// keep the paths whose names start with this prefix
+    #[argh(option)]
+    prefix: Option<String>,
+
+    /// keep the paths whose names match this regular expression
+    #[argh(option)]
+    regex: Option<String>,
+
+    /// drop unused segments and links and renumber segments
+    #[argh(switch, short = 'c')]
+    compact: bool,
+}
+
+pub fn somepaths(
+    gfa: &flatgfa::FlatGFA,
+    args: SomePaths,
+) -> Result<flatgfa::HeapGFAStore, &'static str> {
+    if args.path.is_empty() && args.file.is_none() && args.prefix.is_none() && args.regex.is_none()
+    {
+        return Err("no paths selected");
+    }
+
+    // Collect all the names to match exactly.
+    let mut names: HashSet<BString> = args.path.into_iter().collect();
+    if let Some(filename) = args.file {
+        let file = std::fs::read(filename).or(Err("could not read path list"))?;
+        for line in memfile::MemchrSplit::new(b'\n', &file) {
+            if !line.is_empty() {
+                names.insert(line.into());
+            }
+        }
+    }
+    let regex = match args.regex {
+        Some(re) => Some(regex::bytes::Regex::new(&re).or(Err("invalid regex"))?),
+        None => None,
+    };
+
+    // Keep paths that match any of the criteria.
+    let paths = ops::subset::select_paths(gfa, |name| {
+        names.contains(name)
+            || args
+                .prefix
+                .as_ref()
+                .is_some_and(|p| name.starts_with(p.as_bytes()))
+            || regex.as_ref().is_some_and(|re| re.is_match(name))
+    });
+    Ok(ops::subset::subset_paths(gfa, &paths, args.compact))
+}
+
+/// construct a path-by-segment coverage matrix
+#[derive(FromArgs, PartialEq, Debug)]
+#[argh(subcommand, name = "path-matrix")]
+pub struct PathMatrix {
+    /// output format: tsv (dense), mtx (MatrixMarket), or bin (binary CSR)
+    #[argh(option, short = 'f', default = "String::from(\"tsv\")")]
+    format: String,
+
+    /// only include the named path (default: all paths)
+    #[argh(option, short = 'p')]
+    path: Vec<BString>,
+}
+
+pub fn path_matrix(gfa: &flatgfa::FlatGFA, args: PathMatrix) -> Result<(), &'static str> {
+    let matrix = if args.path.is_empty() {
+        ops::path_matrix::path_matrix(gfa, gfa.paths.ids())
+    } else {
+        let path_ids = args
+            .path
+            .iter()
+            .map(|n| gfa.find_path(n.as_ref()).ok_or("path not found"))
+            .collect::<Result<Vec<_>, _>>()?;
+        ops::path_matrix::path_matrix(gfa, path_ids.into_iter())
+    };
+
+    let stdout = std::io::stdout();
+    let mut out = std::io::BufWriter::new(stdout.lock());
+    match args.format.as_str() {
+        "tsv" => matrix.write_tsv(gfa, &mut out),
+        "mtx" => matrix.write_mtx(&mut out),
+        "bin" => matrix.write_binary(&mut out),
+        _ => return Err("format must be tsv, mtx, or bin"),
+    }
+    .unwrap();
+    Ok(())
+}
+
+/// normalize a graph into a canonical form
+#[derive(FromArgs, PartialEq, Debug)]
+#[argh(subcommand, name = "norm")]
+pub struct Norm {}
+
+/// Merge simple segment chains, flip backward-only segments, and deduplicate links
+pub fn norm(gfa: &flatgfa::FlatGFA, _args: Norm) -> Result<flatgfa::HeapGFAStore, &'static str> {
+    Ok(ops::norm::norm(gfa))
+}
+
+/// compare two FlatGFA graphs
+#[derive(FromArgs, PartialEq, Debug)]
+#[argh(subcommand, name = "diff")]
+pub struct Diff {
+    /// the original graph
+    #[argh(positional)]
+    old: String,
+
+    /// the new graph
+    #[argh(positional)]
+    new: String,
+}
+
+/// Report the segments, links, and paths that differ between two graphs
+pub fn diff(args: Diff) -> Result<(), &'static str> {
+    let inputs = Inputs::open(&[args.old, args.new])?;
+    let graphs = inputs.views()?;
+    ops::diff::diff(&graphs[0], &graphs[1]).print();
+    Ok(())
+}
+
+/// find superbubbles (variant sites) in the graph
+#[derive(FromArgs, PartialEq, Debug)]
+#[argh(subcommand, name = "bubbles")]
+pub struct Bubbles {
+    /// list each path's traversals through the bubbles instead
+    #[argh(switch, short = 't')]
+    traversals: bool,
+
+    /// ignore bubbles containing more than this many handles
+    #[argh(option, short = 'm', default = "10000")]
+    max_size: usize,
+}
+
+pub fn bubbles(gfa: &flatgfa::FlatGFA, args: Bubbles) {
+    let bubbles = ops::bubble::superbubbles(gfa, args.max_size);
+    let traversals = ops::bubble::traversals(gfa, &bubbles);
+    if args.traversals {
+        ops::bubble::TraversalTable { gfa, traversals }.print();
+    } else {
+        ops::bubble::BubbleTable {
+            gfa,
+            bubbles,
+            traversals,
+        }
+        .print();
+    }
+}
+
+/// write variants against a reference path as VCF
+#[derive(FromArgs, PartialEq, Debug)]
+#[argh(subcommand, name = "deconstruct")]
+pub struct Deconstruct {
+    /// the name of the reference path
+    #[argh(option, short = 'r')]
+    reference: BString,
+
+    /// ignore bubbles containing more than this many handles
+    #[argh(option, short = 'm', default = "10000")]
+    max_size: usize,
+}
+
+pub fn deconstruct(gfa: &flatgfa::FlatGFA, args: Deconstruct) -> Result<(), &'static str> {
+    let ref_path = gfa
+        .find_path(args.reference.as_ref())
+        .ok_or("reference path not found")?;
+    let samples = ops::deconstruct::Samples::new(gfa, ref_path);
+    let sites = ops::deconstruct::deconstruct(gfa, ref_path, &samples, args.max_size);
+    ops::deconstruct::Vcf {
+        gfa,
+        ref_path,
+        samples,
+        sites,
+    }
+    .print();
+    Ok(())
+}
+
+/// compute pairwise similarity between paths
+#[derive(FromArgs, PartialEq, Debug)]
+#[argh(subcommand, name = "similarity")]
+pub struct Similarity {
+    /// output format: tsv (one line per pair) or condensed (Jaccard distances)
+    #[argh(option, short = 'f', default = "String::from(\"tsv\")")]
+    format: String,
+
+    /// group paths by sample, haplotype, or contig (from PanSN path names)
+    #[argh(option, short = 'g', long = "group-by")]
+    group_by: Option<GroupBy>,
+}
+
+pub fn similarity(gfa: &flatgfa::FlatGFA, args: Similarity) -> Result<(), &'static str> {
+    use ops::similarity::{similarity, CondensedTable, SimilarityTable};
+
+    let (names, sets): (Vec<BString>, Vec<Vec<Id<flatgfa::Path>>>) = match args.group_by {
+        Some(by) => {
+            let groups = PathGroups::new(gfa, by);
+            (groups.names, groups.paths)
+        }
+        None => gfa
+            .paths
+            .items()
+            .map(|(id, path)| (gfa.get_path_name(path).into(), vec![id]))
+            .unzip(),
+    };
+    let sim = similarity(gfa, &sets);
+    match args.format.as_str() {
+        "tsv" => SimilarityTable { names, sim }.print(),
+        "condensed" => CondensedTable { names, sim }.print(),
+        _ => return Err("format must be tsv or condensed"),
+    }
+    Ok(())
+}
+
+/// summarize paths in fixed-width bins of the pangenome
+#[derive(FromArgs, PartialEq, Debug)]
+#[argh(subcommand, name = "bin")]
+pub struct Bin {
+    /// bin width, in base pairs
+    #[argh(option, short = 'w', default = "10")]
+    width: usize,
+
+    /// write JSON instead of TSV
+    #[argh(switch, short = 'j')]
+    json: bool,
+}
+
+pub fn bin(gfa: &flatgfa::FlatGFA, args: Bin) -> Result<(), &'static str> {
+    if args.width == 0 {
+        return Err("bin width must be positive");
+    }
+    let bins = ops::bin::bin(gfa, args.width);
+    if args.json {
+        ops::bin::BinsJson(bins).print();
+    } else {
+        bins.print();
+    }
+    Ok(())
+}
+
+/// draw the paths along the pangenome as a PNG or SVG image
+#[derive(FromArgs, PartialEq, Debug)]
+#[argh(subcommand, name = "viz")]
+pub struct Viz {
+    /// output image (SVG if the name ends in .svg, PNG otherwise)
+    #[argh(positional)]
+    output: String,
+
+    /// maximum image width, in pixels
+    #[argh(option, short = 'x', default = "1000")]
+    width: usize,
+
+    /// height of each path's row, in pixels
+    #[argh(option, short = 'y', default = "10")]
+    row_height: usize,
+
+    /// only draw the named path (default: all paths)
+    #[argh(option, short = 'p')]
+    path: Vec<BString>,
+
+    /// only draw a `start-end` range of the pangenome
+    #[argh(option, short = 'r')]
+    range: Option<String>,
+
+    /// color by coverage or orientation
+    #[argh(option, short = 'c', default = "ops::viz::ColorBy::Coverage")]
+    color: ops::viz::ColorBy,
+}
+
+pub fn viz(gfa: &flatgfa::FlatGFA, args: Viz) -> Result<(), &'static str> {
+    let paths: Vec<Id<flatgfa::Path>> = if args.path.is_empty() {
+        gfa.paths.ids().collect()
+    } else {
+        args.path
+            .iter()
+            .map(|n| gfa.find_path(n.as_ref()).ok_or("path not found"))
+            .collect::<Result<_, _>>()?
+    };
+    let range = match &args.range {
+        Some(range) => {
+            let (start, end) = range.split_once('-').ok_or("range must be start-end")?;
+            let start: usize = start.parse().map_err(|_| "invalid range start")?;
+            let end: usize = end.parse().map_err(|_| "invalid range end")?;
+            if start >= end {
+                return Err("range must not be empty");
+            }
+            Some(start..end)
+        }
+        None => None,
+    };
+    if args.width == 0 || args.row_height == 0 {
+        return Err("image dimensions must be positive");
+    }
+
+    // The range must overlap the pangenome, or the image would have no columns.
+    let len: usize = gfa.segs.all().iter().map(|s| s.len()).sum();
+    if range.as_ref().map_or(0, |r| r.start) >= len {
+        return Err("range is empty");
+    }
+
+    let layout = ops::viz::layout(gfa, &paths, range, args.width, args.row_height, args.color);
+    let file = std::fs::File::create(&args.output).map_err(|_| "could not create output")?;
+    let mut out = std::io::BufWriter::new(file);
+    if args.output.ends_with(".svg") {
+        ops::viz::Svg(layout)
+            .emit(&mut out)
+            .map_err(|_| "could not write SVG")?;
+    } else {
+        layout
+            .write_png(&mut out)
+            .map_err(|_| "could not write PNG")?;
+    }
+    Ok(())
+}
+
+/// compute a 2D layout with path-guided SGD
+#[derive(FromArgs, PartialEq, Debug)]
+#[argh(subcommand, name = "layout")]
+pub struct Layout {
+    /// output file (TSV if the name ends in .tsv, binary otherwise)
+    #[argh(positional)]
+    output: String,
+
+    /// number of iterations
+    #[argh(option, short = 'n', default = "30")]
+    iters: usize,
+
+    /// number of terms to sample per path step, per iteration
+    #[argh(option, short = 't', default = "10")]
+    terms: usize,
+
+    /// random seed
+    #[argh(option, short = 's', default = "9399220")]
+    seed: u64,
+}
+
+pub fn layout(gfa: &flatgfa::FlatGFA, args: Layout) -> Result<(), &'static str> {
+    use ops::layout::{layout, write_layout, Config, LayoutTable};
+
+    let config = Config {
+        iters: args.iters,
+        terms_per_step: args.terms,
+        seed: args.seed,
+        ..Config::default()
+    };
+    let points = layout(gfa, &config);
+
+    let file = std::fs::File::create(&args.output).map_err(|_| "could not create output")?;
+    let mut out = std::io::BufWriter::new(file);
+    if args.output.ends_with(".tsv") {
+        LayoutTable { gfa, points }.emit(&mut out)
+    } else {
+        write_layout(&points, &mut out)
+    }
+    .map_err(|_| "could not write layout")
+}
+
+/// print the graph (or a region of it) in Graphviz DOT format
+#[derive(FromArgs, PartialEq, Debug)]
+#[argh(subcommand, name = "dot")]
+pub struct Dot {
+    /// only show the region around this segment, as in `extract`
+    #[argh(option, short = 'n')]
+    seg_name: Option<usize>,
+
+    /// number of edges "away" from the segment to include
+    #[argh(option, short = 'c', default = "3")]
+    link_distance: usize,
+
+    /// include segment sequences in the labels
+    #[argh(switch, short = 's')]
+    seq: bool,
+
+    /// draw the path traversals as colored edges
+    #[argh(switch, short = 'p')]
+    paths: bool,
+}
+
+pub fn dot(gfa: &flatgfa::FlatGFA, args: Dot) -> Result<(), &'static str> {
+    let print = |gfa: &flatgfa::FlatGFA| {
+        ops::dot::Dot {
+            gfa,
+            show_seq: args.seq,
+            show_paths: args.paths,
+        }
+        .print()
+    };
+    match args.seg_name {
+        Some(seg_name) => {
+            let origin_seg = gfa.find_seg(seg_name).ok_or("segment not found")?;
+            let mut subgraph = ops::extract::SubgraphBuilder::new(gfa);
+            subgraph.extract(
+                origin_seg,
+                args.link_distance,
+                ops::extract::MAX_DISTANCE_SUBPATHS,
+                ops::extract::MAX_MERGING_ITERATIONS,
+            );
+            print(&subgraph.store.as_ref());
+        }
+        None => print(gfa),
+    }
+    Ok(())
+}
+
+/// remove low-depth segments, short tips, and unused links
+#[derive(FromArgs, PartialEq, Debug)]
+#[argh(subcommand, name = "prune")]
+pub struct Prune {
+    /// remove segments that paths traverse fewer than this many times
+    #[argh(option, short = 'd', default = "0")]
+    min_depth: usize,
+
+    /// remove dangling tips shorter than this many base pairs
+    #[argh(option, short = 't', default = "0")]
+    tip_len: usize,
+
+    /// remove links that no path follows
+    #[argh(switch, short = 'l')]
+    links: bool,
+
+    /// write the report of removed elements here (default: stderr)
+    #[argh(option, short = 'r')]
+    report: Option<String>,
+}
+
+pub fn prune(gfa: &flatgfa::FlatGFA, args: Prune) -> Result<flatgfa::HeapGFAStore, &'static str> {
+    let criteria = ops::prune::Criteria {
+        min_depth: args.min_depth,
+        max_tip_len: args.tip_len,
+        unused_links: args.links,
+    };
+    let (store, report) = ops::prune::prune(gfa, &criteria);
+    match args.report {
+        Some(name) => {
+            let file = std::fs::File::create(name).map_err(|_| "could not create report")?;
+            report.emit(&mut std::io::BufWriter::new(file))
+        }
+        None => report.emit(&mut std::io::stderr().lock()),
+    }
+    .map_err(|_| "could not write report")?;
+    Ok(store)
+}
+
+/// find the weakly connected components and optionally write each to a file
+#[derive(FromArgs, PartialEq, Debug)]
+#[argh(subcommand, name = "components")]
+pub struct Components {
+    /// write each component to `<prefix>.<N>.flatgfa`
+    #[argh(option, short = 'p')]
+    prefix: Option<String>,
+
+    /// write GFA text files (`<prefix>.<N>.gfa`) instead
+    #[argh(switch, short = 'g')]
+    gfa: bool,
+
+    /// keep the original segment names instead of renumbering them
+    #[argh(switch, long = "keep-ids")]
+    keep_ids: bool,
+}
+
+pub fn components(gfa: &flatgfa::FlatGFA, args: Components) -> Result<(), &'static str> {
+    use ops::component::{component_graph, components, ComponentTable};
+
+    let comps = components(gfa);
+    if let Some(prefix) = &args.prefix {
+        for (idx, segs) in comps.iter().enumerate() {
+            let store = component_graph(gfa, segs, !args.keep_ids);
+            let ext = if args.gfa { "gfa" } else { "flatgfa" };
+            write_graph(&store.as_ref(), &format!("{prefix}.{idx}.{ext}"), args.gfa)?;
+        }
+    }
+    ComponentTable { gfa, comps: &comps }.print();
+    Ok(())
+}
+
+/// Several input graphs, named on the command line. Each may be a FlatGFA file,
+/// a graph in an archive (`archive#name`), or a GFA text file ending in `.gfa`.
+struct Inputs {
+    files: Vec<(memmap::Mmap, Option<String>)>,
+    texts: Vec<Option<flatgfa::HeapGFAStore>>,
+}
+
+impl Inputs {
+    fn open(names: &[String]) -> Result<Self, &'static str> {
+        let mut files = Vec::with_capacity(names.len());
+        let mut texts = Vec::with_capacity(names.len());
+        for name in names {
+            let (file, graph) = archive::split_name(name);
+            if !std::path::Path::new(file).exists() {
+                return Err("input file not found");
+            }
+            let mmap = map_file(file);
+            // Parse text inputs up front, so we can borrow them all at once.
+            texts.push(
+                file.ends_with(".gfa")
+                    .then(|| crate::parse::Parser::for_heap().parse_mem(mmap.as_ref())),
+            );
+            files.push((mmap, graph.map(String::from)));
+        }
+        Ok(Self { files, texts })
+    }
+
+    fn views(&self) -> Result<Vec<flatgfa::FlatGFA<'_>>, &'static str> {
+        self.files
+            .iter()
+            .zip(&self.texts)
+            .map(|((mmap, graph), text)| match text {
+                Some(store) => Ok(store.as_ref()),
+                None => archive::view(mmap, graph.as_deref()),
+            })
+            .collect()
+    }
+}
+
+/// combine several graphs into one
+#[derive(FromArgs, PartialEq, Debug)]
+#[argh(subcommand, name = "merge")]
+pub struct Merge {
+    /// the graphs to merge (FlatGFA, or GFA text if the name ends in `.gfa`)
+    #[argh(positional)]
+    graphs: Vec<String>,
+
+    /// shift each graph's segment names past the previous graphs' names
+    #[argh(switch, short = 'o')]
+    offset: bool,
+
+    /// merge segments with the same name and sequence
+    #[argh(switch, short = 'd')]
+    dedup: bool,
+}
+
+pub fn merge(args: Merge) -> Result<flatgfa::HeapGFAStore, &'static str> {
+    use ops::merge::Naming;
+    if args.offset && args.dedup {
+        return Err("--offset and --dedup cannot be combined");
+    }
+
+    let inputs = Inputs::open(&args.graphs)?;
+    let graphs = inputs.views()?;
+
+    let naming = if args.offset {
+        Naming::Offset
+    } else {
+        Naming::Collisions
+    };
+    ops::merge::merge(&graphs, naming, args.dedup)
+}
+
+/// bundle several graphs into one archive file, or list an archive's contents
+#[derive(FromArgs, PartialEq, Debug)]
+#[argh(subcommand, name = "archive")]
+pub struct Archive {
+    /// the archive file to write (or, with --list, to read)
+    #[argh(positional)]
+    archive: String,
+
+    /// the graphs to include, named after their files (or `name=file`)
+    #[argh(positional)]
+    graphs: Vec<String>,
+
+    /// list the graphs in an existing archive
+    #[argh(switch, short = 'l')]
+    list: bool,
+}
+
+pub fn archive(args: Archive) -> Result<(), &'static str> {
+    if args.list {
+        let mmap = map_file(&args.archive);
+        println!("#name	segments	links	paths");
+        for (name, bytes) in archive::entries(&mmap)? {
+            let gfa = file::view(bytes);
+            println!(
+                "{}	{}	{}	{}",
+                name,
+                gfa.segs.len(),
+                gfa.links.len(),
+                gfa.paths.len()
+            );
+        }
+        return Ok(());
+    }
+
+    // Name each graph explicitly or after its file, minus the directory and extension.
+    let (names, files): (Vec<String>, Vec<String>) = args
+        .graphs
+        .iter()
+        .map(|arg| match arg.split_once('=') {
+            Some((name, file)) => (name.to_string(), file.to_string()),
+            None => {
+                let name = match archive::split_name(arg) {
+                    (_, Some(graph)) => graph.to_string(),
+                    (file, None) => std::path::Path::new(file)
+                        .file_stem()
+                        .unwrap()
+                        .to_string_lossy()
+                        .into_owned(),
+                };
+                (name, arg.clone())
+            }
+        })
+        .unzip();
+    if names.iter().collect::<HashSet<_>>().len() != names.len() {
+        return Err("duplicate graph name");
+    }
+
+    let inputs = Inputs::open(&files)?;
+    let graphs: Vec<_> = names
+        .iter()
+        .map(|name| name.as_bytes())
+        .zip(inputs.views()?)
+        .collect();
+    let mut mmap = memfile::map_new_file(&args.archive, archive::size(&graphs) as u64);
+    archive::dump(&graphs, &mut mmap);
+    mmap.flush().map_err(|_| "could not write archive")
+}
+
+/// add a consensus path that follows the highest-coverage walk through each bubble
+#[derive(FromArgs, PartialEq, Debug)]
+#[argh(subcommand, name = "consensus")]
+pub struct Consensus {
+    /// name for the new path
+    #[argh(option, short = 'n', default = "String::from(\"consensus\")")]
+    name: String,
+
+    /// also write the consensus sequence to this FASTA file
+    #[argh(option, short = 'f')]
+    fasta: Option<String>,
+
+    /// in the FASTA file, wrap sequence lines at this width (0 for no wrapping)
+    #[argh(option, short = 'w', default = "80")]
+    width: usize,
+
+    /// treat bubbles with more than this many handles as ordinary branches
+    #[argh(option, short = 'm', default = "10000")]
+    max_size: usize,
+}
+
+pub fn consensus(
+    gfa: &flatgfa::FlatGFA,
+    args: Consensus,
+) -> Result<flatgfa::HeapGFAStore, &'static str> {
+    if gfa.find_path(args.name.as_bytes().into()).is_some() {
+        return Err("a path with that name already exists");
+    }
+    let walk = ops::consensus::consensus(gfa, args.max_size);
+    let store = ops::consensus::add_path(gfa, args.name.as_bytes(), &walk);
+
+    if let Some(fasta) = args.fasta {
+        let new = store.as_ref();
+        let path = new.find_path(args.name.as_bytes().into()).unwrap();
+        let file = std::fs::File::create(fasta).map_err(|_| "could not create FASTA file")?;
+        let selections = [PathSelection { path, range: None }];
+        ops::fasta::paths_to_fasta(
+            &new,
+            &selections,
+            args.width,
+            &mut std::io::BufWriter::new(file),
+        )
+        .map_err(|_| "could not write FASTA file")?;
+    }
+    Ok(store)
+}
+
+/// build, save, or query an index of the k-mers in the graph
+#[derive(FromArgs, PartialEq, Debug)]
+#[argh(subcommand, name = "kmers")]
+pub struct Kmers {
+    /// k-mer length, up to 31 (default: the saved index's, or 21)
+    #[argh(option, short = 'k')]
+    k: Option<usize>,
+
+    /// save the index in the input FlatGFA file, replacing any saved index
+    #[argh(switch, short = 's')]
+    save: bool,
+
+    /// look up the k-mers in this sequence
+    #[argh(option, short = 'q')]
+    query: Vec<String>,
+}
+
+pub fn kmers(gfa: &flatgfa::FlatGFA, input: Option<&str>, args: Kmers) -> Result<(), &'static str> {
+    use ops::kmer::{KmerIndex, MAX_K};
+
+    // Only plain FlatGFA files (not text or archives) can hold a saved index.
+    let file = match input {
+        Some(name) if archive::split_name(name).1.is_none() => Some((name, map_file(name))),
+        _ => None,
+    };
+    if args.save && file.is_none() {
+        return Err("saving an index requires a FlatGFA input file (-i)");
+    }
+
+    let saved = file
+        .as_ref()
+        .and_then(|(_, mmap)| KmerIndex::saved(mmap))
+        .filter(|index| args.k.is_none_or(|k| k == index.k));
+    let index = match saved {
+        Some(index) if !args.save => index,
+        _ => {
+            let k = args.k.unwrap_or(21);
+            if k == 0 || k > MAX_K {
+                return Err("k must be between 1 and 31");
+            }
+            KmerIndex::build(gfa, k)
+        }
+    };
+
+    if args.save {
+        let (name, mmap) = file.as_ref().unwrap();
+        let graph_len = mmap.len() - file::trailer(mmap).len();
+        let out = std::fs::OpenOptions::new()
+            .write(true)
+            .open(name)
+            .map_err(|_| "could not open input file")?;
+        out.set_len(graph_len as u64)
+            .map_err(|_| "could not write index")?;
+        let mut out = std::io::BufWriter::new(out);
+        out.seek(std::io::SeekFrom::End(0))
+            .map_err(|_| "could not write index")?;
+        index.write(&mut out).map_err(|_| "could not write index")?;
+    }
+
+    if !args.query.is_empty() {
+        let queries: Vec<Vec<u8>> = args.query.into_iter().map(String::into_bytes).collect();
+        ops::kmer::QueryTable {
+            gfa,
+            index: &index,
+            queries: &queries,
+        }
+        .print();
+    } else if !args.save {
+        println!("#k	kmers	positions");
+        println!("{}	{}	{}", index.k, index.distinct(), index.entries.len());
+    }
+    Ok(())
+}
+
+/// find exact occurrences of a sequence (or its reverse complement) in the graph
+#[derive(FromArgs, PartialEq, Debug)]
+#[argh(subcommand, name = "find-seq")]
+pub struct FindSeq {
+    /// the sequence to search for
+    #[argh(positional)]
+    query: String,
+
+    /// report occurrences in paths, with path coordinates
+    #[argh(switch, short = 'p')]
+    paths: bool,
+}
+
+pub fn find_seq(gfa: &flatgfa::FlatGFA, args: FindSeq) -> Result<(), &'static str> {
+    use ops::find_seq::{find_in_graph, find_in_paths, GraphMatchTable, PathMatchTable};
+
+    let query = args.query.as_bytes();
+    if query.is_empty() {
+        return Err("the query must not be empty");
+    }
+    if args.paths {
+        let matches = find_in_paths(gfa, query);
+        PathMatchTable { gfa, matches }.print();
+    } else {
+        let matches = find_in_graph(gfa, query);
+        GraphMatchTable { gfa, matches }.print();
+    }
+    Ok(())
+}
+
+/// sequence composition: GC fraction, N count, and softmasked (lowercase) count
+#[derive(FromArgs, PartialEq, Debug)]
+#[argh(subcommand, name = "composition")]
+pub struct Composition {
+    /// show paths instead of segments
+    #[argh(switch, short = 'P')]
+    paths: bool,
+
+    /// show windows of this size along paths
+    #[argh(option, short = 'w')]
+    window: Option<usize>,
+
+    /// show only the named path (with -P or -w)
+    #[argh(option, short = 'r')]
+    path: Vec<BString>,
+
+    /// with -w, print a bedGraph of one metric: gc, n, or lowercase
+    #[argh(option, short = 'g')]
+    bedgraph: Option<ops::composition::Metric>,
+
+    /// show length distributions and totals for segments and paths
+    #[argh(switch, short = 'S')]
+    summarize: bool,
+}
+
+pub fn composition(gfa: &flatgfa::FlatGFA, args: Composition) -> Result<(), &'static str> {
+    use ops::composition::{
+        path_composition, seg_composition, window_composition, PathTable, SegTable, Summary,
+        WindowTable,
+    };
+    use ops::stats::LengthDist;
+
+    if [args.paths, args.window.is_some(), args.summarize]
+        .iter()
+        .filter(|&&b| b)
+        .count()
+        > 1
+    {
+        return Err("choose one of -P, -w, and -S");
+    }
+    if args.bedgraph.is_some() && args.window.is_none() {
+        return Err("-g requires -w");
+    }
+    if !(args.path.is_empty() || args.paths || args.window.is_some()) {
+        return Err("-r requires -P or -w");
+    }
+    if args.window == Some(0) {
+        return Err("window size must be positive");
+    }
+
+    let paths: Vec<Id<flatgfa::Path>> = if args.path.is_empty() {
+        gfa.paths.ids().collect()
+    } else {
+        args.path
+            .iter()
+            .map(|name| gfa.find_path(name.as_ref()).ok_or("path not found"))
+            .collect::<Result<_, _>>()?
+    };
+
+    let seg_comps = seg_composition(gfa);
+    if args.summarize {
+        let mut seg_total = ops::composition::Composition::default();
+        seg_comps.iter().for_each(|c| seg_total.add(c));
+        let path_comps: Vec<_> = paths
+            .iter()
+            .map(|&p| path_composition(gfa, &seg_comps, p))
+            .collect();
+        let mut path_total = ops::composition::Composition::default();
+        path_comps.iter().for_each(|c| path_total.add(c));
+        Summary {
+            segs: (
+                LengthDist::new(seg_comps.iter().map(|c| c.len).collect()),
+                seg_total,
+            ),
+            paths: (
+                LengthDist::new(path_comps.iter().map(|c| c.len).collect()),
+                path_total,
+            ),
+        }
+        .print();
+    } else if let Some(size) = args.window {
+        let windows = paths
+            .iter()
+            .map(|&p| (p, window_composition(gfa, p, size)))
+            .collect();
+        WindowTable {
+            gfa,
+            windows,
+            bedgraph: args.bedgraph,
+        }
+        .print();
+    } else if args.paths {
+        let comps = paths
+            .iter()
+            .map(|&p| (p, path_composition(gfa, &seg_comps, p)))
+            .collect();
+        PathTable { gfa, comps }.print();
+    } else {
+        SegTable {
+            gfa,
+            comps: seg_comps,
+        }
+        .print();
+    }
+    Ok(())
+}
@@ -2,12 +2,12 @@ use argh::FromArgs;
 use flatgfa::flatgfa::FlatGFA;
 use flatgfa::parse::Parser;
 use flatgfa::pool::Store;
-use flatgfa::{cli::cmds, file, memfile, parse};
+use flatgfa::{archive, cli::cmds, file, memfile, parse};
 
 #[derive(FromArgs)]
 /// Convert between GFA text and FlatGFA binary formats.
 struct PolBin {
-    /// read from a binary FlatGFA file
+    /// read from a binary FlatGFA file (or `archive#name` for one graph in an archive)
     #[argh(option, short = 'i')]
     input: Option<String>,
 
@@ -52,6 +52,26 @@ enum Command {
     SeqImport(cmds::SeqImport),
     PangenotypeMatrix(cmds::PangenotypeMatrix),
     WindowDepth(cmds::WindowDepth),
+    Overlap(cmds::Overlap),
+    SomePaths(cmds::SomePaths),
+    PathMatrix(cmds::PathMatrix),
+    Norm(cmds::Norm),
+    Diff(cmds::Diff),
+    Bubbles(cmds::Bubbles),
+    Deconstruct(cmds::Deconstruct),
+    Similarity(cmds::Similarity),
+    Bin(cmds::Bin),
+    Viz(cmds::Viz),
+    Layout(cmds::Layout),
+    Dot(cmds::Dot),
+    Prune(cmds::Prune),
+    Components(cmds::Components),
+    Merge(cmds::Merge),
+    Archive(cmds::Archive),
+    Consensus(cmds::Consensus),
+    Kmers(cmds::Kmers),
+    FindSeq(cmds::FindSeq),
+    Composition(cmds::Composition),
 }
 
 fn main() -> Result<(), &'static str> {
@@ -84,20 +104,45 @@ fn main() -> Result<(), &'static str> {
         return Ok(());
     }
 
+    // Comparing graphs takes two inputs of its own.
+    if let Some(Command::Diff(sub_args)) = args.command {
+        cmds::diff(sub_args)?;
+        return Ok(());
+    }
+
+    // Merging also reads its own inputs.
+    if let Some(Command::Merge(sub_args)) = args.command {
+        let store = cmds::merge(sub_args)?;
+        dump(&store.as_ref(), &args.output, &args.output_gfa);
+        return Ok(());
+    }
+
+    // As does bundling graphs into an archive.
+    if let Some(Command::Archive(sub_args)) = args.command {
+        return cmds::archive(sub_args);
+    }
+
     // Load the input from a file (binary) or stdin (text).
     let mmap;
     let mut mmap_mut;
     let store;
     let slice_store;
-    let gfa = match args.input {
+    let gfa = match &args.input {
         Some(name) => {
+            let (name, graph) = archive::split_name(name);
+            if !std::path::Path::new(name).exists() {
+                return Err("input file not found");
+            }
             if args.mutate {
-                mmap_mut = memfile::map_file_mut(&name);
+                if graph.is_some() {
+                    return Err("cannot mutate a graph in an archive");
+                }
+                mmap_mut = memfile::map_file_mut(name);
                 slice_store = file::view_store(&mut mmap_mut);
                 slice_store.as_ref()
             } else {
-                mmap = memfile::map_file(&name);
-                file::view(&mmap)
+                mmap = memfile::map_file(name);
+                archive::view(&mmap, graph)?
             }
         }
         None => {
@@ -120,21 +165,22 @@ fn main() -> Result<(), &'static str> {
         Some(Command::Toc(sub_args)) => {
             cmds::toc(&gfa, sub_args);
         }
-        Some(Command::Paths(_)) => {
-            cmds::paths(&gfa);
+        Some(Command::Paths(sub_args)) => {
+            cmds::paths(&gfa, sub_args)?;
         }
         Some(Command::Stats(sub_args)) => {
-            cmds::stats(&gfa, sub_args);
+            cmds::stats(&gfa, sub_args)?;
         }
         Some(Command::Position(sub_args)) => {
             cmds::position(&gfa, sub_args)?;
         }
         Some(Command::Extract(sub_args)) => {
-            let store = cmds::extract(&gfa, sub_args)?;
-            dump(&store.as_ref(), &args.output, &args.output_gfa);
+            if let Some(store) = cmds::extract(&gfa, sub_args)? {
+                dump(&store.as_ref(), &args.output, &args.output_gfa);
+            }
         }
         Some(Command::Depth(sub_args)) => {
-            cmds::depth(&gfa, sub_args);
+            cmds::depth(&gfa, sub_args)?;
         }
         Some(Command::Chop(sub_args)) => {
             let store = cmds::chop(&gfa, sub_args)?;
@@ -178,6 +224,70 @@ fn main() -> Result<(), &'static str> {
         Some(Command::WindowDepth(sub_args)) => {
             cmds::window_depth(&gfa, sub_args);
         }
+        Some(Command::Overlap(sub_args)) => {
+            cmds::overlap(&gfa, sub_args)?;
+        }
+        Some(Command::SomePaths(sub_args)) => {
+            let store = cmds::somepaths(&gfa, sub_args)?;
+            dump(&store.as_ref(), &args.output, &args.output_gfa);
+        }
+        Some(Command::PathMatrix(sub_args)) => {
+            cmds::path_matrix(&gfa, sub_args)?;
+        }
+        Some(Command::Norm(sub_args)) => {
+            let store = cmds::norm(&gfa, sub_args)?;
+            dump(&store.as_ref(), &args.output, &args.output_gfa);
+        }
+        Some(Command::Diff(_sub_args)) => {
+            panic!("Unreachable code");
+        }
+        Some(Command::Bubbles(sub_args)) => {
+            cmds::bubbles(&gfa, sub_args);
+        }
+        Some(Command::Deconstruct(sub_args)) => {
+            cmds::deconstruct(&gfa, sub_args)?;
+        }
+        Some(Command::Similarity(sub_args)) => {
+            cmds::similarity(&gfa, sub_args)?;
+        }
+        Some(Command::Bin(sub_args)) => {
+            cmds::bin(&gfa, sub_args)?;
+        }
+        Some(Command::Viz(sub_args)) => {
+            cmds::viz(&gfa, sub_args)?;
+        }
+        Some(Command::Layout(sub_args)) => {
+            cmds::layout(&gfa, sub_args)?;
+        }
+        Some(Command::Dot(sub_args)) => {
+            cmds::dot(&gfa, sub_args)?;
+        }
+        Some(Command::Prune(sub_args)) => {
+            let store = cmds::prune(&gfa, sub_args)?;
+            dump(&store.as_ref(), &args.output, &args.output_gfa);
+        }
+        Some(Command::Components(sub_args)) => {
+            cmds::components(&gfa, sub_args)?;
+        }
+        Some(Command::Merge(_sub_args)) => {
+            panic!("Unreachable code");
+        }
+        Some(Command::Archive(_sub_args)) => {
+            panic!("Unreachable code");
+        }
+        Some(Command::Consensus(sub_args)) => {
+            let store = cmds::consensus(&gfa, sub_args)?;
+            dump(&store.as_ref(), &args.output, &args.output_gfa);
+        }
+        Some(Command::Kmers(sub_args)) => {
+            cmds::kmers(&gfa, args.input.as_deref(), sub_args)?;
+        }
+        Some(Command::FindSeq(sub_args)) => {
+            cmds::find_seq(&gfa, sub_args)?;
+        }
+        Some(Command::Composition(sub_args)) => {
+            cmds::composition(&gfa, sub_args)?;
+        }
         None => {
             // Just emit the GFA or FlatGFA file.
             dump(&gfa, &args.output, &args.output_gfa);
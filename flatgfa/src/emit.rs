@@ -17,3 +17,19 @@ pub trait Emit {
         self.emit(&mut std::io::stdout().lock()).unwrap();
     }
 }
+
+/// Write a byte string as a quoted JSON string, escaping as necessary.
+pub fn write_json_str(f: &mut impl Write, s: &[u8]) -> Result<()> {
+    f.write_all(b"\"")?;
+    for &c in s {
+        match c {
+            b'"' => f.write_all(b"\\\"")?,
+            b'\\' => f.write_all(b"\\\\")?,
+            b'\n' => f.write_all(b"\\n")?,
+            b'\t' => f.write_all(b"\\t")?,
+            0..=0x1f => write!(f, "\\u{:04x}", c)?,
+            _ => f.write_all(&[c])?,
+        }
+    }
+    f.write_all(b"\"")
+}
@@ -306,6 +306,12 @@ pub fn dump(gfa: &flatgfa::FlatGFA, buf: &mut [u8]) {
     write_bytes(rest, gfa.line_order.all()).unwrap();
 }
 
+/// Get the data that follows the FlatGFA in a buffer, such as an index section.
+pub fn trailer(data: &[u8]) -> &[u8] {
+    let (toc, _) = read_toc(data);
+    &data[toc.size()..]
+}
+
 /// Get the total size in bytes of a FlatGFA structure. This should result in a big
 /// enough buffer to write the entire FlatGFA into with `dump`.
 pub fn size(gfa: &flatgfa::FlatGFA) -> usize {
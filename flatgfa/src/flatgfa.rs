@@ -3,6 +3,7 @@
 use std::ops::Range;
 use std::str::FromStr;
 
+use crate::pansn::PanSN;
 use crate::pool::{self, Id, Pool, Span, Store};
 use bstr::BStr;
 use num_enum::{IntoPrimitive, TryFromPrimitive};
@@ -146,7 +147,9 @@ impl Link {
 }
 
 /// A forward or backward direction.
-#[derive(Debug, PartialEq, IntoPrimitive, TryFromPrimitive)]
+#[derive(
+    Debug, PartialEq, Eq, Hash, Clone, Copy, PartialOrd, Ord, IntoPrimitive, TryFromPrimitive,
+)]
 #[repr(u8)]
 pub enum Orientation {
     Forward,  // +
@@ -183,7 +186,9 @@ impl From<bool> for Orientation {
 /// A Handle refers to the forward (+) or backward (-) orientation for a given segment.
 /// So, logically, it consists of a pair of a segment reference (usize) and an
 /// orientation (1 bit). We pack the two values into a single word.
-#[derive(Debug, FromBytes, IntoBytes, Clone, Copy, PartialEq, Eq, Hash, Immutable)]
+#[derive(
+    Debug, FromBytes, IntoBytes, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Immutable,
+)]
 #[repr(packed)]
 pub struct Handle(u32);
 
@@ -206,6 +211,11 @@ impl Handle {
     pub fn orient(&self) -> Orientation {
         ((self.0 & 1) as u8).try_into().unwrap()
     }
+
+    /// Get the handle for the same segment in the opposite orientation.
+    pub fn flip(&self) -> Self {
+        Self(self.0 ^ 1)
+    }
 }
 
 /// The kind of each operation in a CIGAR alignment.
@@ -327,6 +337,25 @@ impl<'a> Sequence<'a> {
             self.data.to_vec()
         }
     }
+
+    /// Append the represented sequence data to an existing vector.
+    pub fn extend_vec(&self, vec: &mut Vec<u8>) {
+        if self.revcmp {
+            vec.extend(self.data.iter().rev().map(|&c| nucleotide_complement(c)));
+        } else {
+            vec.extend_from_slice(self.data);
+        }
+    }
+
+    /// The number of base pairs in the sequence.
+    pub fn len(&self) -> usize {
+        self.data.len()
+    }
+
+    /// Check whether the sequence is empty.
+    pub fn is_empty(&self) -> bool {
+        self.data.is_empty()
+    }
 }
 
 /// Given an ASCII character for a nucleotide, get its complement.
@@ -393,6 +422,11 @@ impl<'a> FlatGFA<'a> {
         self.name_data[path.name].as_ref()
     }
 
+    /// Parse the name of a path according to the PanSN convention, if it follows it.
+    pub fn get_path_pansn(&self, path: &Path) -> Option<PanSN<'_>> {
+        PanSN::parse(self.get_path_name(path))
+    }
+
     pub fn get_path_steps(&self, path: &Path) -> impl Iterator<Item = &Handle> {
         self.steps[path.steps].iter()
     }
@@ -1,3 +1,4 @@
+pub mod archive;
 pub mod cli;
 pub mod emit;
 pub mod file;
@@ -8,6 +9,7 @@ pub mod memfile;
 pub mod namemap;
 pub mod ops;
 pub mod packedseq;
+pub mod pansn;
 pub mod parse;
 pub mod pool;
 pub mod print;
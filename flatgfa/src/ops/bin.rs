@@ -0,0 +1,250 @@
+use crate::emit::{write_json_str, Emit};
+use crate::flatbed::FlatBED;
+use crate::flatgfa::{self, Orientation, Path};
+use crate::ops::depth::format_float;
+use crate::ops::window_depth::{assign_depths, SegmentDepth, Windows};
+use crate::pool::Id;
+use bstr::BStr;
+use rayon::prelude::*;
+use std::io::Write;
+use std::ops::Range;
+
+/// The summary of one path's traversal of a single bin.
+pub struct Bin {
+    /// The 0-based index of the bin.
+    pub bin: usize,
+
+    /// The mean number of times the path covers each base in the bin.
+    pub coverage: f64,
+
+    /// The fraction of that coverage where the path is in the reverse orientation.
+    pub inversion: f64,
+
+    /// The mean (1-based) position in the path of the covered bases.
+    pub position: f64,
+}
+
+/// The bins for all the paths in a graph.
+pub struct Bins<'a> {
+    pub gfa: &'a flatgfa::FlatGFA<'a>,
+
+    /// The width of every bin (except maybe the last one).
+    pub width: usize,
+
+    /// The total length of the pangenome.
+    pub len: usize,
+
+    /// The bins that each path covers, in order.
+    pub paths: Vec<(Id<Path>, Vec<Bin>)>,
+}
+
+/// Summarize a single path's bins.
+///
+/// We use the same weighted-overlap logic as `window_depth`. But here, the
+/// "segments" are the distinct segments in the path, at their offsets in the
+/// pangenome, weighted by coverage, inversions, or positions. Dividing the
+/// latter two by coverage gives per-base means. The position of every base in
+/// a segment is approximated by the mean position of that segment.
+fn path_bins(
+    gfa: &flatgfa::FlatGFA,
+    seg_offsets: &[usize],
+    path: Id<Path>,
+    windows: &FlatBED,
+) -> Vec<Bin> {
+    // Find every visit to every segment, sorted by segment (i.e., pangenome offset).
+    let mut visits = Vec::new();
+    let mut pos = 0;
+    for step in gfa.get_path_steps(&gfa.paths[path]) {
+        let seg = step.segment().index();
+        visits.push((seg, pos, step.orient() == Orientation::Backward));
+        pos += gfa.segs[step.segment()].len();
+    }
+    visits.sort_unstable_by_key(|v| v.0);
+
+    let mut covs = Vec::new();
+    let mut invs = Vec::new();
+    let mut poss = Vec::new();
+    for chunk in visits.chunk_by(|a, b| a.0 == b.0) {
+        let seg = chunk[0].0;
+        let len = seg_offsets[seg + 1] - seg_offsets[seg];
+        let range = (seg_offsets[seg], seg_offsets[seg + 1]);
+        let inverted = chunk.iter().filter(|v| v.2).count();
+        let mid: f64 = chunk
+            .iter()
+            .map(|v| v.1 as f64 + (len + 1) as f64 / 2.0)
+            .sum();
+        covs.push(SegmentDepth {
+            depth: (chunk.len() * len) as f64,
+            range,
+        });
+        invs.push(SegmentDepth {
+            depth: (inverted * len) as f64,
+            range,
+        });
+        poss.push(SegmentDepth {
+            depth: mid * len as f64,
+            range,
+        });
+    }
+
+    let covs = assign_depths(covs, windows);
+    let invs = assign_depths(invs, windows);
+    let poss = assign_depths(poss, windows);
+    covs.iter()
+        .enumerate()
+        .filter(|(_, &cov)| cov > 0.0)
+        .map(|(bin, &cov)| Bin {
+            bin,
+            coverage: cov,
+            inversion: invs[bin] / cov,
+            position: poss[bin] / cov,
+        })
+        .collect()
+}
+
+/// Get the offset of every segment in the pangenome, plus the total length.
+///
+/// The pangenome's coordinate space is the concatenation of all the segments,
+/// in order. So the last element is the length of the whole pangenome.
+pub fn seg_offsets(gfa: &flatgfa::FlatGFA) -> Vec<usize> {
+    let mut offsets = Vec::with_capacity(gfa.segs.len() + 1);
+    let mut len = 0;
+    offsets.push(0);
+    for seg in gfa.segs.all() {
+        len += seg.len();
+        offsets.push(len);
+    }
+    offsets
+}
+
+/// Split the pangenome into fixed-width bins and summarize every path's
+/// traversal of each bin, like `odgi bin`.
+///
+/// Paths are summarized in parallel.
+pub fn bin<'a>(gfa: &'a flatgfa::FlatGFA<'a>, width: usize) -> Bins<'a> {
+    let paths: Vec<Id<Path>> = gfa.paths.ids().collect();
+    bin_range(gfa, &paths, None, width)
+}
+
+/// Summarize some paths in bins that cover part of the pangenome.
+///
+/// The bins start at the beginning of `range` (or the whole pangenome, if
+/// `None`), and they are numbered from there.
+pub fn bin_range<'a>(
+    gfa: &'a flatgfa::FlatGFA<'a>,
+    paths: &[Id<Path>],
+    range: Option<Range<usize>>,
+    width: usize,
+) -> Bins<'a> {
+    let seg_offsets = seg_offsets(gfa);
+    let len = *seg_offsets.last().unwrap();
+    let range = range.unwrap_or(0..len);
+
+    let windows = Windows {
+        name: BStr::new("pangenome"),
+        start: range.start as u64,
+        end: range.end.min(len) as u64,
+        size: width as u64,
+    }
+    .as_bed();
+    let windows = windows.as_ref();
+
+    let paths = paths
+        .par_iter()
+        .map(|&path| (path, path_bins(gfa, &seg_offsets, path, &windows)))
+        .collect();
+    Bins {
+        gfa,
+        width,
+        len,
+        paths,
+    }
+}
+
+impl Emit for Bins<'_> {
+    /// Write a TSV with a line for every bin that every path covers. Bins are
+    /// numbered from 1, as in `odgi bin`.
+    fn emit(self, f: &mut impl Write) -> std::io::Result<()> {
+        writeln!(f, "#path.name\tbin\tmean.cov\tmean.inv\tmean.pos")?;
+        for (path, bins) in &self.paths {
+            let name = self.gfa.get_path_name(&self.gfa.paths[*path]);
+            for bin in bins {
+                writeln!(
+                    f,
+                    "{}\t{}\t{}\t{}\t{}",
+                    name,
+                    bin.bin + 1,
+                    format_float(bin.coverage, 4),
+                    format_float(bin.inversion, 4),
+                    format_float(bin.position, 1),
+                )?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// The same bins, as a JSON document.
+///
+/// Each path gets a list of `[bin, mean.cov, mean.inv, mean.pos]` arrays.
+pub struct BinsJson<'a>(pub Bins<'a>);
+
+impl Emit for BinsJson<'_> {
+    fn emit(self, f: &mut impl Write) -> std::io::Result<()> {
+        let bins = self.0;
+        writeln!(
+            f,
+            "{{\"bin_width\": {}, \"length\": {}, \"paths\": [",
+            bins.width, bins.len
+        )?;
+        for (i, (path, path_bins)) in bins.paths.iter().enumerate() {
+            write!(f, "  {{\"name\": ")?;
+            write_json_str(f, bins.gfa.get_path_name(&bins.gfa.paths[*path]))?;
+            write!(f, ", \"bins\": [")?;
+            for (j, bin) in path_bins.iter().enumerate() {
+                if j > 0 {
+                    write!(f, ", ")?;
+                }
+                write!(
+                    f,
+                    "[{}, {}, {}, {}]",
+                    bin.bin + 1,
+                    format_float(bin.coverage, 4),
+                    format_float(bin.inversion, 4),
+                    format_float(bin.position, 1),
+                )?;
+            }
+            let sep = if i + 1 < bins.paths.len() { "," } else { "" };
+            writeln!(f, "]}}{sep}")?;
+        }
+        writeln!(f, "]}}")
+    }
+}
+
+#[test]
+fn test_bin() {
+    // The pangenome is AAAA|CC|GGGG, with bins of width 4.
+    let gfa_text = b"S\t1\tAAAA\nS\t2\tCC\nS\t3\tGGGG\n\
+        P\tx\t1+,2+,3+\t*\nP\ty\t3-,1-,1-\t*\n";
+    let store = crate::parse::Parser::for_heap().parse_mem(gfa_text);
+    let gfa = store.as_ref();
+    let bins = bin(&gfa, 4);
+    assert_eq!(bins.len, 10);
+
+    let (_, x) = &bins.paths[0];
+    assert_eq!(x.len(), 3);
+    assert_eq!(x[0].coverage, 1.0);
+    assert_eq!(x[0].position, 2.5);
+    assert_eq!(x[1].coverage, 1.0);
+    assert_eq!(x[2].coverage, 1.0);
+    assert_eq!(x[2].bin, 2);
+
+    // Path y covers segment 1 twice, backward, and misses segment 2.
+    let (_, y) = &bins.paths[1];
+    assert_eq!(y.len(), 3);
+    assert_eq!(y[0].coverage, 2.0);
+    assert_eq!(y[0].inversion, 1.0);
+    assert_eq!(y[0].position, 8.5);
+    assert_eq!(y[1].coverage, 0.5);
+    assert_eq!(y[2].coverage, 1.0);
+}
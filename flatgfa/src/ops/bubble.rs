@@ -0,0 +1,386 @@
+use crate::emit::Emit;
+use crate::flatgfa::{self, Handle, Orientation, Path, Segment};
+use crate::pool::Id;
+use crate::print::Display;
+use rayon::prelude::*;
+use std::collections::{HashMap, HashSet};
+use std::io::Write;
+use std::ops::Range;
+
+/// A superbubble: a region of the graph between an entrance and an exit handle.
+///
+/// Every walk that leaves the entrance reaches the exit without leaving the
+/// region, there are no cycles inside, and nothing else enters or leaves. The
+/// exit is the closest such handle to the entrance.
+pub struct Bubble {
+    pub entrance: Handle,
+    pub exit: Handle,
+
+    /// The segments strictly between the entrance and exit.
+    pub interior: Vec<Id<Segment>>,
+
+    /// The innermost other bubble that contains this one, as an index.
+    pub parent: Option<usize>,
+
+    /// The number of bubbles this one is nested inside (0 for top-level bubbles).
+    pub depth: usize,
+}
+
+/// One pass of a path through a bubble.
+pub struct Traversal {
+    /// The index of the bubble.
+    pub bubble: usize,
+    pub path: Id<Path>,
+
+    /// The steps in the path, including the entrance and exit.
+    pub steps: Range<usize>,
+
+    /// Whether the path goes from the exit to the entrance (in the opposite orientation).
+    pub reverse: bool,
+}
+
+/// The directed graph of handles, as implied by the links.
+pub struct HandleGraph {
+    succs: Vec<Vec<Handle>>,
+}
+
+/// A dense index for a handle.
+fn handle_idx(handle: Handle) -> usize {
+    handle.segment().index() * 2 + u8::from(handle.orient()) as usize
+}
+
+impl HandleGraph {
+    /// Build the graph from a FlatGFA's links. Each link A→B also implies B̄→Ā.
+    pub fn new(gfa: &flatgfa::FlatGFA) -> Self {
+        let mut succs = vec![Vec::new(); gfa.segs.len() * 2];
+        for link in gfa.links.all() {
+            succs[handle_idx(link.from)].push(link.to);
+            succs[handle_idx(link.to.flip())].push(link.from.flip());
+        }
+        for list in &mut succs {
+            list.sort_unstable();
+            list.dedup();
+        }
+        Self { succs }
+    }
+
+    /// The handles that follow a handle.
+    pub fn succs(&self, handle: Handle) -> &[Handle] {
+        &self.succs[handle_idx(handle)]
+    }
+
+    /// The handles that precede a handle.
+    pub fn preds(&self, handle: Handle) -> impl Iterator<Item = Handle> + '_ {
+        self.succs(handle.flip()).iter().map(|h| h.flip())
+    }
+
+    /// Look for the superbubble that starts at a given entrance.
+    ///
+    /// This is the search from Onodera et al., "Detecting Superbubbles in
+    /// Assembly Graphs" (WABI 2013): we visit handles in topological order,
+    /// starting at the entrance, and succeed when exactly one handle is left
+    /// on the frontier. We give up after visiting `max_size` handles. Returns
+    /// the exit and the handles visited along the way (including the entrance).
+    fn find_exit(&self, entrance: Handle, max_size: usize) -> Option<(Handle, HashSet<Handle>)> {
+        // Skip trivial "bubbles" with only one way through.
+        if self.succs(entrance).len() < 2 {
+            return None;
+        }
+
+        let mut visited = HashSet::new();
+        let mut seen = HashSet::from([entrance]);
+        let mut stack = vec![entrance];
+        while let Some(v) = stack.pop() {
+            visited.insert(v);
+            seen.remove(&v);
+            if visited.len() > max_size {
+                return None;
+            }
+
+            let succs = self.succs(v);
+            if succs.is_empty() {
+                return None; // A tip.
+            }
+            for &u in succs {
+                if u == entrance || visited.contains(&u) {
+                    return None; // A cycle.
+                }
+                seen.insert(u);
+                if self.preds(u).all(|p| visited.contains(&p)) {
+                    stack.push(u);
+                }
+            }
+
+            if stack.len() == 1 && seen.len() == 1 {
+                let exit = stack[0];
+                if self.succs(exit).contains(&entrance) {
+                    return None;
+                }
+                return Some((exit, visited));
+            }
+        }
+        None
+    }
+}
+
+/// A sort key for choosing between the two directions of a bubble.
+///
+/// We prefer forward handles, then smaller entrance handles.
+fn preferred(entrance: Handle, exit: Handle) -> (usize, Handle) {
+    let backward = [entrance, exit]
+        .iter()
+        .filter(|h| h.orient() == Orientation::Backward)
+        .count();
+    (backward, entrance)
+}
+
+/// Find all the superbubbles in a graph.
+///
+/// Every superbubble can be read in two directions: from A to B or from B̄ to Ā.
+/// We report each bubble once, preferring the direction where the entrance and
+/// exit are forward handles. Bubbles are sorted by entrance, and bubbles with more than
+/// `max_size` handles are ignored. The search is parallel over entrances.
+pub fn superbubbles(gfa: &flatgfa::FlatGFA, max_size: usize) -> Vec<Bubble> {
+    let graph = HandleGraph::new(gfa);
+    let handles: Vec<Handle> = gfa
+        .segs
+        .ids()
+        .flat_map(|id| {
+            [
+                id.handle(Orientation::Forward),
+                id.handle(Orientation::Backward),
+            ]
+        })
+        .collect();
+
+    let mut bubbles: Vec<Bubble> = handles
+        .into_par_iter()
+        .filter_map(|entrance| {
+            let (exit, visited) = graph.find_exit(entrance, max_size)?;
+            if preferred(exit.flip(), entrance.flip()) < preferred(entrance, exit) {
+                return None; // We'll find this one from the other direction.
+            }
+            let mut interior: Vec<Id<Segment>> = visited
+                .into_iter()
+                .filter(|&h| h != entrance)
+                .map(|h| h.segment())
+                .collect();
+            interior.sort_unstable_by_key(|id| id.index());
+            interior.dedup();
+            Some(Bubble {
+                entrance,
+                exit,
+                interior,
+                parent: None,
+                depth: 0,
+            })
+        })
+        .collect();
+    bubbles.sort_unstable_by_key(|b| b.entrance);
+
+    nest(gfa, &mut bubbles);
+    bubbles
+}
+
+/// Compute the parent and depth of each bubble.
+///
+/// A nested bubble's entrance is inside its parent, so the parent is the
+/// smallest bubble whose interior contains that entrance.
+fn nest(gfa: &flatgfa::FlatGFA, bubbles: &mut [Bubble]) {
+    let mut by_size: Vec<usize> = (0..bubbles.len()).collect();
+    by_size.sort_by_key(|&i| std::cmp::Reverse(bubbles[i].interior.len()));
+
+    // Visit larger bubbles first, so each segment ends up with the innermost
+    // bubble that contains it. Parents are always strictly larger, so they
+    // already have their depths when we get to their children.
+    let mut innermost: Vec<Option<usize>> = vec![None; gfa.segs.len()];
+    for i in by_size {
+        let parent = innermost[bubbles[i].entrance.segment().index()];
+        bubbles[i].parent = parent;
+        bubbles[i].depth = parent.map_or(0, |p| bubbles[p].depth + 1);
+        for seg in &bubbles[i].interior {
+            innermost[seg.index()] = Some(i);
+        }
+    }
+}
+
+/// Find every path's traversals of the bubbles.
+///
+/// A path can pass through a bubble in either direction: from its entrance to
+/// its exit, or from the flipped exit to the flipped entrance. Paths are
+/// scanned in parallel. The traversals are sorted by bubble, then path, then
+/// position.
+pub fn traversals(gfa: &flatgfa::FlatGFA, bubbles: &[Bubble]) -> Vec<Traversal> {
+    // The bubbles (and directions) that start at each handle.
+    let mut starts: HashMap<Handle, Vec<(usize, bool)>> = HashMap::new();
+    for (i, bubble) in bubbles.iter().enumerate() {
+        starts.entry(bubble.entrance).or_default().push((i, false));
+        starts
+            .entry(bubble.exit.flip())
+            .or_default()
+            .push((i, true));
+    }
+
+    let interiors: Vec<HashSet<Id<Segment>>> = bubbles
+        .iter()
+        .map(|b| b.interior.iter().copied().collect())
+        .collect();
+
+    let mut travs: Vec<Traversal> = gfa
+        .paths
+        .items()
+        .collect::<Vec<_>>()
+        .into_par_iter()
+        .flat_map_iter(|(path_id, path)| {
+            let steps = &gfa.steps[path.steps];
+            let mut found = Vec::new();
+            for (i, step) in steps.iter().enumerate() {
+                for &(bubble, reverse) in starts.get(step).map_or(&[][..], |v| v) {
+                    let b = &bubbles[bubble];
+                    let end = if reverse { b.entrance.flip() } else { b.exit };
+
+                    // Follow the path until it reaches the exit or leaves the
+                    // bubble. It may visit interior segments more than once.
+                    for (j, s) in steps.iter().enumerate().skip(i + 1) {
+                        if *s == end {
+                            found.push(Traversal {
+                                bubble,
+                                path: path_id,
+                                steps: i..j + 1,
+                                reverse,
+                            });
+                            break;
+                        }
+                        if !interiors[bubble].contains(&s.segment()) {
+                            break;
+                        }
+                    }
+                }
+            }
+            found
+        })
+        .collect();
+    travs.sort_unstable_by_key(|t| (t.bubble, t.path.index(), t.steps.start));
+    travs
+}
+
+/// A printable list of bubbles.
+///
+/// Each line has an ID, the entrance and exit handles, the nesting depth, the
+/// parent bubble's ID (or `*`), the number of interior segments, and the
+/// number of path traversals.
+pub struct BubbleTable<'a> {
+    pub gfa: &'a flatgfa::FlatGFA<'a>,
+    pub bubbles: Vec<Bubble>,
+    pub traversals: Vec<Traversal>,
+}
+
+impl Emit for BubbleTable<'_> {
+    fn emit(self, f: &mut impl Write) -> std::io::Result<()> {
+        let mut counts = vec![0; self.bubbles.len()];
+        for trav in &self.traversals {
+            counts[trav.bubble] += 1;
+        }
+
+        writeln!(
+            f,
+            "#id\tentrance\texit\tdepth\tparent\tsegments\ttraversals"
+        )?;
+        for (i, bubble) in self.bubbles.iter().enumerate() {
+            let parent = match bubble.parent {
+                Some(p) => p.to_string(),
+                None => "*".to_string(),
+            };
+            writeln!(
+                f,
+                "{}\t{}\t{}\t{}\t{}\t{}\t{}",
+                i,
+                Display(self.gfa, bubble.entrance),
+                Display(self.gfa, bubble.exit),
+                bubble.depth,
+                parent,
+                bubble.interior.len(),
+                counts[i],
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// A printable list of path traversals through bubbles.
+///
+/// Each line has the bubble ID, the path name, the step range in the path
+/// (half-open, including the entrance and exit steps), and the walk itself,
+/// oriented from the bubble's entrance to its exit.
+pub struct TraversalTable<'a> {
+    pub gfa: &'a flatgfa::FlatGFA<'a>,
+    pub traversals: Vec<Traversal>,
+}
+
+impl Emit for TraversalTable<'_> {
+    fn emit(self, f: &mut impl Write) -> std::io::Result<()> {
+        writeln!(f, "#id\tpath\tstart\tend\twalk")?;
+        for trav in self.traversals {
+            let path = &self.gfa.paths[trav.path];
+            let steps = &self.gfa.steps[path.steps][trav.steps.clone()];
+            let walk: Vec<String> = if trav.reverse {
+                steps
+                    .iter()
+                    .rev()
+                    .map(|h| Display(self.gfa, h.flip()).to_string())
+                    .collect()
+            } else {
+                steps
+                    .iter()
+                    .map(|h| Display(self.gfa, *h).to_string())
+                    .collect()
+            };
+            writeln!(
+                f,
+                "{}\t{}\t{}\t{}\t{}",
+                trav.bubble,
+                self.gfa.get_path_name(path),
+                trav.steps.start,
+                trav.steps.end,
+                walk.join(","),
+            )?;
+        }
+        Ok(())
+    }
+}
+
+#[test]
+fn test_superbubbles() {
+    // A bubble (7 to 4, through 2 or 3) nested in another bubble (1 to 6),
+    // with a path that crosses the outer bubble backward and one that visits
+    // segment 2 in both orientations.
+    let gfa_text = b"S\t1\tA\nS\t2\tC\nS\t3\tG\nS\t4\tT\nS\t5\tA\nS\t6\tC\nS\t7\tG\n\
+        L\t1\t+\t7\t+\t0M\nL\t7\t+\t2\t+\t0M\nL\t7\t+\t3\t+\t0M\nL\t2\t+\t4\t+\t0M\n\
+        L\t3\t+\t4\t+\t0M\nL\t4\t+\t6\t+\t0M\nL\t1\t+\t5\t+\t0M\nL\t5\t+\t6\t+\t0M\n\
+        P\tx\t1+,7+,2+,4+,6+\t*\nP\ty\t6-,5-,1-\t*\nP\tz\t1+,7+,2+,2-,2+,4+,6+\t*\n";
+    let store = crate::parse::Parser::for_heap().parse_mem(gfa_text);
+    let gfa = store.as_ref();
+    let bubbles = superbubbles(&gfa, 100);
+    assert_eq!(bubbles.len(), 2);
+
+    let name = |h: Handle| (gfa.get_handle_seg(h).name, h.orient());
+    let outer = bubbles.iter().position(|b| b.depth == 0).unwrap();
+    let inner = 1 - outer;
+    assert_eq!(name(bubbles[outer].entrance), (1, Orientation::Forward));
+    assert_eq!(name(bubbles[outer].exit), (6, Orientation::Forward));
+    assert_eq!(bubbles[outer].interior.len(), 5);
+    assert_eq!(name(bubbles[inner].entrance), (7, Orientation::Forward));
+    assert_eq!(name(bubbles[inner].exit), (4, Orientation::Forward));
+    assert_eq!(bubbles[inner].parent, Some(outer));
+    assert_eq!(bubbles[inner].depth, 1);
+
+    let travs = traversals(&gfa, &bubbles);
+    let outer_travs: Vec<_> = travs.iter().filter(|t| t.bubble == outer).collect();
+    assert_eq!(outer_travs.len(), 3);
+    assert_eq!(outer_travs[0].steps, 0..5);
+    assert!(outer_travs[1].reverse);
+    assert_eq!(outer_travs[2].steps, 0..7);
+    let inner_travs: Vec<_> = travs.iter().filter(|t| t.bubble == inner).collect();
+    assert_eq!(inner_travs.len(), 2);
+    assert_eq!(inner_travs[1].steps, 1..6);
+}
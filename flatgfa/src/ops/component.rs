@@ -0,0 +1,124 @@
+use crate::emit::Emit;
+use crate::flatgfa::{self, Segment};
+use crate::ops::extract::SubgraphBuilder;
+use crate::pool::Id;
+use std::io::Write;
+
+/// A union-find forest over segment indices.
+struct UnionFind(Vec<usize>);
+
+impl UnionFind {
+    fn new(size: usize) -> Self {
+        Self((0..size).collect())
+    }
+
+    fn find(&mut self, mut x: usize) -> usize {
+        while self.0[x] != x {
+            self.0[x] = self.0[self.0[x]]; // Path halving.
+            x = self.0[x];
+        }
+        x
+    }
+
+    fn union(&mut self, a: usize, b: usize) {
+        let (a, b) = (self.find(a), self.find(b));
+        // Keep the smaller index as the root.
+        if a < b {
+            self.0[b] = a;
+        } else {
+            self.0[a] = b;
+        }
+    }
+}
+
+/// Find the weakly connected components of a graph, using its links.
+///
+/// Each component is a list of segments in ID order, and the components are
+/// ordered by their first segment.
+pub fn components(gfa: &flatgfa::FlatGFA) -> Vec<Vec<Id<Segment>>> {
+    let mut uf = UnionFind::new(gfa.segs.len());
+    for link in gfa.links.all() {
+        uf.union(link.from.segment().index(), link.to.segment().index());
+    }
+
+    let mut comp_of_root: Vec<Option<usize>> = vec![None; gfa.segs.len()];
+    let mut comps: Vec<Vec<Id<Segment>>> = Vec::new();
+    for id in gfa.segs.ids() {
+        let root = uf.find(id.index());
+        let comp = *comp_of_root[root].get_or_insert_with(|| {
+            comps.push(Vec::new());
+            comps.len() - 1
+        });
+        comps[comp].push(id);
+    }
+    comps
+}
+
+/// Build a separate graph for a single component, like `odgi explode`.
+///
+/// With `renumber`, the segments are renamed 1, 2, 3, ... in order.
+pub fn component_graph(
+    gfa: &flatgfa::FlatGFA,
+    segs: &[Id<Segment>],
+    renumber: bool,
+) -> flatgfa::HeapGFAStore {
+    let mut subgraph = SubgraphBuilder::new(gfa);
+    subgraph.add_header();
+    subgraph.extract_segs(segs, renumber);
+    subgraph.store
+}
+
+/// A table with the size of each component.
+pub struct ComponentTable<'a> {
+    pub gfa: &'a flatgfa::FlatGFA<'a>,
+    pub comps: &'a [Vec<Id<Segment>>],
+}
+
+impl Emit for ComponentTable<'_> {
+    fn emit(self, f: &mut impl Write) -> std::io::Result<()> {
+        // Count the paths that start in each component.
+        let mut comp_of_seg = vec![0; self.gfa.segs.len()];
+        for (idx, comp) in self.comps.iter().enumerate() {
+            for seg in comp {
+                comp_of_seg[seg.index()] = idx;
+            }
+        }
+        let mut paths = vec![0; self.comps.len()];
+        for path in self.gfa.paths.all() {
+            if let Some(first) = self.gfa.steps[path.steps].first() {
+                paths[comp_of_seg[first.segment().index()]] += 1;
+            }
+        }
+
+        writeln!(f, "#component\tsegments\tlength\tpaths")?;
+        for (idx, comp) in self.comps.iter().enumerate() {
+            let len: usize = comp.iter().map(|s| self.gfa.segs[*s].len()).sum();
+            writeln!(f, "{}\t{}\t{}\t{}", idx, comp.len(), len, paths[idx])?;
+        }
+        Ok(())
+    }
+}
+
+#[test]
+fn test_components() {
+    // Two chains: 1-3 and 2-4-5, with a path through each.
+    let gfa_text = b"S\t1\tA\nS\t2\tCC\nS\t3\tG\nS\t4\tTT\nS\t5\tA\n\
+        L\t1\t+\t3\t+\t0M\nL\t2\t+\t4\t-\t0M\nL\t5\t+\t4\t+\t0M\n\
+        P\tx\t1+,3+\t*\nP\ty\t2+,4-,5-\t*\n";
+    let store = crate::parse::Parser::for_heap().parse_mem(gfa_text);
+    let gfa = store.as_ref();
+
+    let comps = components(&gfa);
+    assert_eq!(comps.len(), 2);
+    assert_eq!(comps[0].len(), 2);
+    assert_eq!(comps[1].len(), 3);
+
+    let sub = component_graph(&gfa, &comps[1], true);
+    let sub = sub.as_ref();
+    assert_eq!(sub.segs.len(), 3);
+    assert_eq!(sub.links.len(), 2);
+    assert_eq!(sub.paths.len(), 1);
+    assert_eq!(sub.get_path_name(&sub.paths.all()[0]), "y");
+    let names: Vec<usize> = sub.segs.all().iter().map(|s| s.name).collect();
+    assert_eq!(names, vec![1, 2, 3]);
+}
@@ -0,0 +1,315 @@
+use crate::emit::Emit;
+use crate::flatgfa::{self, Orientation, Path};
+use crate::ops::depth::format_float;
+use crate::ops::position::path_len;
+use crate::ops::stats::LengthDist;
+use crate::ops::window_depth::Windows;
+use crate::pool::Id;
+use std::io::Write;
+use std::str::FromStr;
+
+/// Counts of the kinds of bases in a sequence.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct Composition {
+    pub len: usize,
+
+    /// G and C bases (in either case).
+    pub gc: usize,
+
+    /// N bases (in either case).
+    pub n: usize,
+
+    /// Lowercase (softmasked) bases.
+    pub lower: usize,
+}
+
+impl Composition {
+    pub fn count(seq: &[u8]) -> Self {
+        let mut comp = Self {
+            len: seq.len(),
+            ..Self::default()
+        };
+        for &c in seq {
+            match c.to_ascii_uppercase() {
+                b'G' | b'C' => comp.gc += 1,
+                b'N' => comp.n += 1,
+                _ => {}
+            }
+            if c.is_ascii_lowercase() {
+                comp.lower += 1;
+            }
+        }
+        comp
+    }
+
+    pub fn add(&mut self, other: &Self) {
+        self.len += other.len;
+        self.gc += other.gc;
+        self.n += other.n;
+        self.lower += other.lower;
+    }
+
+    /// The fraction of G and C among the bases that are not N, or 0 if there
+    /// are none.
+    pub fn gc_fraction(&self) -> f64 {
+        let called = self.len - self.n;
+        if called == 0 {
+            0.0
+        } else {
+            self.gc as f64 / called as f64
+        }
+    }
+}
+
+/// Count the bases in every segment.
+pub fn seg_composition(gfa: &flatgfa::FlatGFA) -> Vec<Composition> {
+    gfa.segs
+        .all()
+        .iter()
+        .map(|seg| Composition::count(gfa.get_seq(seg)))
+        .collect()
+}
+
+/// Count the bases in a path's sequence, given the counts for each segment.
+pub fn path_composition(
+    gfa: &flatgfa::FlatGFA,
+    seg_comp: &[Composition],
+    path: Id<Path>,
+) -> Composition {
+    let mut comp = Composition::default();
+    for step in gfa.get_path_steps(&gfa.paths[path]) {
+        comp.add(&seg_comp[step.segment().index()]);
+    }
+    comp
+}
+
+/// Count the bases in each piece of a path, splitting steps wherever they
+/// cross a multiple of `size`, so every piece falls inside a single window.
+fn path_pieces(
+    gfa: &flatgfa::FlatGFA,
+    path: Id<Path>,
+    size: usize,
+) -> Vec<((usize, usize), Composition)> {
+    let mut pieces = Vec::new();
+    let mut pos = 0;
+    for step in gfa.get_path_steps(&gfa.paths[path]) {
+        let seq = gfa.get_seq(gfa.get_handle_seg(*step));
+        let step_end = pos + seq.len();
+        let mut start = pos;
+        while start < step_end {
+            let end = ((start / size + 1) * size).min(step_end);
+
+            // Counts don't depend on the strand, but the range within the segment does.
+            let (a, b) = (start - pos, end - pos);
+            let range = match step.orient() {
+                Orientation::Forward => a..b,
+                Orientation::Backward => seq.len() - b..seq.len() - a,
+            };
+            pieces.push(((start, end), Composition::count(&seq[range])));
+            start = end;
+        }
+        pos = step_end;
+    }
+    pieces
+}
+
+/// The composition of a window along a path.
+pub struct Window {
+    pub start: u64,
+    pub end: u64,
+    pub comp: Composition,
+}
+
+/// Count the bases in equally-sized windows along a path.
+///
+/// The window coordinates come from `window_depth::Windows`. Because no piece
+/// of the path straddles two windows, each piece's counts go straight into the
+/// window it starts in.
+pub fn window_composition(gfa: &flatgfa::FlatGFA, path: Id<Path>, size: usize) -> Vec<Window> {
+    let mut windows: Vec<Window> = Windows {
+        name: gfa.get_path_name(&gfa.paths[path]),
+        start: 0,
+        end: path_len(gfa, &gfa.paths[path]) as u64,
+        size: size as u64,
+    }
+    .ranges()
+    .map(|(start, end)| Window {
+        start,
+        end,
+        comp: Composition::default(),
+    })
+    .collect();
+    for ((start, _), comp) in path_pieces(gfa, path, size) {
+        windows[start / size].comp.add(&comp);
+    }
+    windows
+}
+
+/// A single value to report for each window in a bedGraph.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Metric {
+    Gc,
+    N,
+    Lowercase,
+}
+
+impl FromStr for Metric {
+    type Err = &'static str;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "gc" => Ok(Self::Gc),
+            "n" => Ok(Self::N),
+            "lowercase" => Ok(Self::Lowercase),
+            _ => Err("metric must be gc, n, or lowercase"),
+        }
+    }
+}
+
+impl Metric {
+    fn format(&self, comp: &Composition) -> String {
+        match self {
+            Self::Gc => format_float(comp.gc_fraction(), 4),
+            Self::N => comp.n.to_string(),
+            Self::Lowercase => comp.lower.to_string(),
+        }
+    }
+}
+
+fn write_comp(f: &mut impl Write, comp: &Composition) -> std::io::Result<()> {
+    writeln!(
+        f,
+        "\t{}\t{}\t{}\t{}",
+        comp.len,
+        format_float(comp.gc_fraction(), 4),
+        comp.n,
+        comp.lower
+    )
+}
+
+/// The composition of every segment, as a TSV.
+pub struct SegTable<'a> {
+    pub gfa: &'a flatgfa::FlatGFA<'a>,
+    pub comps: Vec<Composition>,
+}
+
+impl Emit for SegTable<'_> {
+    fn emit(self, f: &mut impl Write) -> std::io::Result<()> {
+        writeln!(f, "#seg\tlength\tgc\tn\tlowercase")?;
+        for (seg, comp) in self.gfa.segs.all().iter().zip(&self.comps) {
+            let name = seg.name;
+            write!(f, "{name}")?;
+            write_comp(f, comp)?;
+        }
+        Ok(())
+    }
+}
+
+/// The composition of some paths, as a TSV.
+pub struct PathTable<'a> {
+    pub gfa: &'a flatgfa::FlatGFA<'a>,
+    pub comps: Vec<(Id<Path>, Composition)>,
+}
+
+impl Emit for PathTable<'_> {
+    fn emit(self, f: &mut impl Write) -> std::io::Result<()> {
+        writeln!(f, "#path\tlength\tgc\tn\tlowercase")?;
+        for (path, comp) in &self.comps {
+            write!(f, "{}", self.gfa.get_path_name(&self.gfa.paths[*path]))?;
+            write_comp(f, comp)?;
+        }
+        Ok(())
+    }
+}
+
+/// The composition of windows along some paths: either a TSV with every count,
+/// or a bedGraph (with no header) of a single metric.
+pub struct WindowTable<'a> {
+    pub gfa: &'a flatgfa::FlatGFA<'a>,
+    pub windows: Vec<(Id<Path>, Vec<Window>)>,
+    pub bedgraph: Option<Metric>,
+}
+
+impl Emit for WindowTable<'_> {
+    fn emit(self, f: &mut impl Write) -> std::io::Result<()> {
+        if self.bedgraph.is_none() {
+            writeln!(f, "#path\tstart\tend\tlength\tgc\tn\tlowercase")?;
+        }
+        for (path, windows) in &self.windows {
+            let name = self.gfa.get_path_name(&self.gfa.paths[*path]);
+            for window in windows {
+                write!(f, "{}\t{}\t{}", name, window.start, window.end)?;
+                match self.bedgraph {
+                    Some(metric) => writeln!(f, "\t{}", metric.format(&window.comp))?,
+                    None => write_comp(f, &window.comp)?,
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// The length distributions and overall composition of the segments and the
+/// paths, as a TSV.
+pub struct Summary {
+    pub segs: (LengthDist, Composition),
+    pub paths: (LengthDist, Composition),
+}
+
+impl Emit for Summary {
+    fn emit(self, f: &mut impl Write) -> std::io::Result<()> {
+        writeln!(
+            f,
+            "#level\tcount\tlength\tmin\tmedian\tmean\tmax\tgc\tn\tlowercase"
+        )?;
+        for (level, (dist, comp)) in [("segments", self.segs), ("paths", self.paths)] {
+            writeln!(
+                f,
+                "{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}",
+                level,
+                dist.count,
+                dist.total,
+                dist.min,
+                format_float(dist.median, 2),
+                format_float(dist.mean, 2),
+                dist.max,
+                format_float(comp.gc_fraction(), 4),
+                comp.n,
+                comp.lower
+            )?;
+        }
+        Ok(())
+    }
+}
+
+#[test]
+fn test_composition() {
+    let gfa_text = b"S\t1\tACGTnn\nS\t2\tggcA\nP\tx\t1+,2-\t*\n";
+    let store = crate::parse::Parser::for_heap().parse_mem(gfa_text);
+    let gfa = store.as_ref();
+
+    let segs = seg_composition(&gfa);
+    assert_eq!(
+        segs[0],
+        Composition {
+            len: 6,
+            gc: 2,
+            n: 2,
+            lower: 2
+        }
+    );
+    assert_eq!(segs[1].gc_fraction(), 0.75);
+
+    let x = gfa.find_path("x".into()).unwrap();
+    let path = path_composition(&gfa, &segs, x);
+    assert_eq!((path.len, path.gc, path.n, path.lower), (10, 5, 2, 5));
+
+    // Path x spells ACGTnn|Tgcc, so the windows are ACGT, nnTg, and cc.
+    let windows = window_composition(&gfa, x, 4);
+    let comps: Vec<_> = windows
+        .iter()
+        .map(|w| (w.comp.len, w.comp.gc, w.comp.n, w.comp.lower))
+        .collect();
+    assert_eq!(comps, vec![(4, 2, 0, 0), (4, 1, 2, 3), (2, 2, 0, 2)]);
+    assert_eq!((windows[2].start, windows[2].end), (8, 10));
+}
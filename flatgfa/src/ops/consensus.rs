@@ -0,0 +1,191 @@
+use crate::flatgfa::{self, Handle, Orientation};
+use crate::ops::bubble::{superbubbles, traversals, HandleGraph};
+use crate::ops::depth::seg_depth;
+use crate::ops::norm::{copy_path, new_store};
+use std::collections::{HashMap, HashSet};
+
+/// The walk chosen through a bubble, starting at one of its ends.
+struct Choice {
+    /// The interior handles of the chosen walk, from entrance to exit.
+    walk: Vec<Handle>,
+
+    /// Where the walk ends up.
+    exit: Handle,
+}
+
+/// Score a walk through a bubble: the length-weighted mean depth of its interior
+/// segments. An empty walk (a deletion) has no interior, so it gets the number of
+/// paths that take it instead, which is what its depth would be if it had any.
+fn coverage(gfa: &flatgfa::FlatGFA, depth: &[usize], walk: &[Handle], count: usize) -> f64 {
+    let len: usize = walk.iter().map(|h| gfa.get_handle_seg(*h).len()).sum();
+    if len == 0 {
+        return count as f64;
+    }
+    let weighted: usize = walk
+        .iter()
+        .map(|h| depth[h.segment().index()] * gfa.get_handle_seg(*h).len())
+        .sum();
+    weighted as f64 / len as f64
+}
+
+/// Choose the highest-coverage walk through every bubble, in both directions.
+///
+/// The candidates are the walks that paths actually take. The result maps each
+/// handle where a bubble starts to the chosen walk. Where bubbles are nested and
+/// start at the same handle, the outermost one wins.
+fn choose_walks(
+    gfa: &flatgfa::FlatGFA,
+    depth: &[usize],
+    max_size: usize,
+) -> HashMap<Handle, Choice> {
+    let bubbles = superbubbles(gfa, max_size);
+    let travs = traversals(gfa, &bubbles);
+
+    // Group the walks by bubble (oriented from entrance to exit) and count them.
+    let mut walks: Vec<HashMap<Vec<Handle>, usize>> = vec![HashMap::new(); bubbles.len()];
+    for trav in &travs {
+        let steps = &gfa.steps[gfa.paths[trav.path].steps][trav.steps.clone()];
+        let interior = &steps[1..steps.len() - 1];
+        let walk: Vec<Handle> = if trav.reverse {
+            interior.iter().rev().map(|h| h.flip()).collect()
+        } else {
+            interior.to_vec()
+        };
+        *walks[trav.bubble].entry(walk).or_default() += 1;
+    }
+
+    let mut choices = HashMap::new();
+    let mut order: Vec<usize> = (0..bubbles.len()).collect();
+    order.sort_by_key(|&i| std::cmp::Reverse(bubbles[i].depth));
+    for i in order {
+        let bubble = &bubbles[i];
+        let best = walks[i]
+            .iter()
+            .map(|(walk, &count)| (coverage(gfa, depth, walk, count), walk))
+            .max_by(|a, b| a.0.total_cmp(&b.0).then_with(|| b.1.cmp(a.1)));
+        let Some((_, walk)) = best else {
+            continue; // No path goes through this bubble.
+        };
+        let reverse = walk.iter().rev().map(|h| h.flip()).collect();
+
+        // Inner bubbles come first, so outer bubbles overwrite them.
+        choices.insert(
+            bubble.entrance,
+            Choice {
+                walk: walk.clone(),
+                exit: bubble.exit,
+            },
+        );
+        choices.insert(
+            bubble.exit.flip(),
+            Choice {
+                walk: reverse,
+                exit: bubble.entrance.flip(),
+            },
+        );
+    }
+    choices
+}
+
+/// Find a consensus walk through the graph, as a list of handles.
+///
+/// The walk starts at the deepest segment that has nothing before it (or the
+/// deepest segment overall, if every segment has a predecessor). When the walk
+/// reaches the start of a superbubble, it takes the bubble's highest-coverage
+/// walk, where coverage comes from `depth::seg_depth` (see `coverage`). Elsewhere,
+/// it steps to the deepest successor. It never revisits a segment, and it stops
+/// when there is nowhere left to go. Bubbles with more than `max_size` handles
+/// are treated as ordinary branches.
+pub fn consensus(gfa: &flatgfa::FlatGFA, max_size: usize) -> Vec<Handle> {
+    let depth = seg_depth(gfa);
+    let graph = HandleGraph::new(gfa);
+    let choices = choose_walks(gfa, &depth, max_size);
+
+    let Some(start) = gfa
+        .segs
+        .ids()
+        .map(|id| id.handle(Orientation::Forward))
+        .max_by_key(|&h| {
+            let source = graph.preds(h).next().is_none();
+            (source, depth[h.segment().index()], std::cmp::Reverse(h))
+        })
+    else {
+        return Vec::new();
+    };
+
+    let mut walk = vec![start];
+    let mut visited = HashSet::from([start.segment()]);
+    let mut cur = start;
+    loop {
+        if let Some(choice) = choices.get(&cur) {
+            let fresh = choice
+                .walk
+                .iter()
+                .chain([&choice.exit])
+                .all(|h| !visited.contains(&h.segment()));
+            if fresh {
+                walk.extend(&choice.walk);
+                walk.push(choice.exit);
+                visited.extend(choice.walk.iter().map(|h| h.segment()));
+                visited.insert(choice.exit.segment());
+                cur = choice.exit;
+                continue;
+            }
+        }
+
+        let next = graph
+            .succs(cur)
+            .iter()
+            .filter(|h| !visited.contains(&h.segment()))
+            .max_by_key(|h| (depth[h.segment().index()], std::cmp::Reverse(**h)));
+        match next {
+            Some(&next) => {
+                walk.push(next);
+                visited.insert(next.segment());
+                cur = next;
+            }
+            None => break,
+        }
+    }
+    walk
+}
+
+/// Copy a graph and add a new path to it.
+pub fn add_path(gfa: &flatgfa::FlatGFA, name: &[u8], walk: &[Handle]) -> flatgfa::HeapGFAStore {
+    let mut store = new_store(gfa);
+    for seg in gfa.segs.all() {
+        store.add_seg(seg.name, gfa.get_seq(seg), gfa.get_optional_data(seg));
+    }
+    for path in gfa.paths.all() {
+        copy_path(&mut store, gfa, path, true, Some);
+    }
+    let steps = store.add_steps(walk.iter().copied());
+    store.add_path(name, steps, std::iter::empty());
+    for link in gfa.links.all() {
+        let overlap = gfa.get_alignment(link.overlap).ops.to_vec();
+        store.add_link(link.from, link.to, overlap);
+    }
+    store
+}
+
+#[test]
+fn test_consensus() {
+    // A SNP (2 or 3) and an insertion (4) that only one path takes.
+    let gfa_text = b"S\t1\tAAAA\nS\t2\tC\nS\t3\tG\nS\t4\tTTT\nS\t5\tAAAA\n\
+        L\t1\t+\t2\t+\t0M\nL\t1\t+\t3\t+\t0M\nL\t2\t+\t5\t+\t0M\nL\t3\t+\t5\t+\t0M\n\
+        L\t3\t+\t4\t+\t0M\nL\t4\t+\t5\t+\t0M\n\
+        P\tx\t1+,2+,5+\t*\nP\ty\t1+,3+,4+,5+\t*\nP\tz\t5-,2-,1-\t*\n";
+    let store = crate::parse::Parser::for_heap().parse_mem(gfa_text);
+    let gfa = store.as_ref();
+
+    let walk = consensus(&gfa, 100);
+    let names: Vec<usize> = walk.iter().map(|h| gfa.get_handle_seg(*h).name).collect();
+    assert_eq!(names, vec![1, 2, 5]);
+    assert!(walk.iter().all(|h| h.orient() == Orientation::Forward));
+
+    let with = add_path(&gfa, b"consensus", &walk);
+    let with = with.as_ref();
+    assert_eq!(with.paths.len(), 4);
+    let path = &with.paths[with.find_path("consensus".into()).unwrap()];
+    assert_eq!(with.get_path_steps(path).count(), 3);
+}
@@ -0,0 +1,369 @@
+use crate::emit::Emit;
+use crate::flatgfa::{self, Handle, Orientation, Path};
+use crate::ops::bubble::{self, Traversal};
+use crate::ops::position::path_len;
+use crate::pansn::PanSN;
+use crate::pool::Id;
+use bstr::{BStr, BString};
+use std::collections::HashMap;
+use std::io::Write;
+
+/// The samples (and their haplotypes) that get genotype columns in the VCF.
+pub struct Samples {
+    /// The sample names, in order of first appearance.
+    pub names: Vec<BString>,
+
+    /// The haplotype names for each sample, in order of first appearance.
+    pub haps: Vec<Vec<BString>>,
+
+    /// The (sample, haplotype) indices for every path, or `None` for the reference.
+    pub of_path: Vec<Option<(usize, usize)>>,
+}
+
+/// Get the sample and haplotype for a path name, according to PanSN. Names
+/// that don't follow PanSN are a sample with a single haplotype.
+fn sample_hap(name: &BStr) -> (&BStr, &BStr) {
+    match PanSN::parse(name) {
+        Some(pansn) => (pansn.sample, pansn.haplotype.unwrap_or_default()),
+        None => (name, BStr::new("")),
+    }
+}
+
+impl Samples {
+    /// Group the paths in a graph (except for the reference) by sample and haplotype.
+    pub fn new(gfa: &flatgfa::FlatGFA, ref_path: Id<Path>) -> Self {
+        let mut names: Vec<BString> = Vec::new();
+        let mut haps: Vec<Vec<BString>> = Vec::new();
+        let mut index: HashMap<&BStr, usize> = HashMap::new();
+        let mut of_path = Vec::with_capacity(gfa.paths.len());
+        for (id, path) in gfa.paths.items() {
+            if id == ref_path {
+                of_path.push(None);
+                continue;
+            }
+            let (sample, hap) = sample_hap(gfa.get_path_name(path));
+            let s = *index.entry(sample).or_insert_with(|| {
+                names.push(sample.into());
+                haps.push(Vec::new());
+                names.len() - 1
+            });
+            let h = match haps[s].iter().position(|h| h == hap) {
+                Some(h) => h,
+                None => {
+                    haps[s].push(hap.into());
+                    haps[s].len() - 1
+                }
+            };
+            of_path.push(Some((s, h)));
+        }
+        Self {
+            names,
+            haps,
+            of_path,
+        }
+    }
+}
+
+/// A variant site: a bubble where some paths diverge from the reference.
+pub struct Site {
+    /// The 0-based position of the site's first allele base on the reference.
+    pub pos: usize,
+
+    /// The nesting depth of the bubble.
+    pub depth: usize,
+
+    /// The entrance and exit handles, in the reference path's orientation.
+    pub entrance: Handle,
+    pub exit: Handle,
+
+    /// The allele sequences. The first is the reference allele.
+    pub alleles: Vec<Vec<u8>>,
+
+    /// The walk for each allele (in the reference orientation, including the
+    /// entrance and exit).
+    pub walks: Vec<Vec<Handle>>,
+
+    /// The allele for each haplotype of each sample, if it traverses the site.
+    pub genotypes: Vec<Vec<Option<usize>>>,
+
+    /// The reference base to pad the alleles with, if any allele is empty.
+    pub padding: Option<Padding>,
+}
+
+/// A reference base that pads the alleles at a site, as VCF requires when an
+/// allele would otherwise be empty.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Padding {
+    /// The base just before the site, which goes before each allele.
+    Before(u8),
+
+    /// The base just after the site, which goes after each allele. We only use
+    /// this when there is nothing before the site on the reference.
+    After(u8),
+}
+
+/// Find the reference base to pad a site with, skipping over any empty
+/// segments around it. The site's alleles are between the steps `start` and
+/// `end` (exclusive).
+fn padding(gfa: &flatgfa::FlatGFA, steps: &[Handle], start: usize, end: usize) -> Option<Padding> {
+    let before = steps[..start].iter().rev().find_map(|h| {
+        let seq = gfa.get_seq_oriented(*h);
+        (!seq.is_empty()).then(|| seq.index(seq.len() - 1))
+    });
+    let after = || {
+        steps[end..].iter().find_map(|h| {
+            let seq = gfa.get_seq_oriented(*h);
+            (!seq.is_empty()).then(|| seq.index(0))
+        })
+    };
+    before
+        .map(Padding::Before)
+        .or_else(|| after().map(Padding::After))
+}
+
+/// Get a traversal's walk, in either its own orientation or flipped around.
+fn walk(gfa: &flatgfa::FlatGFA, trav: &Traversal, flip: bool) -> Vec<Handle> {
+    let path = &gfa.paths[trav.path];
+    let steps = &gfa.steps[path.steps][trav.steps.clone()];
+    if flip {
+        steps.iter().rev().map(|h| h.flip()).collect()
+    } else {
+        steps.to_vec()
+    }
+}
+
+/// Find the variants between a reference path and all the other paths.
+///
+/// We find the graph's bubbles (see `ops::bubble`) and make a site for every
+/// time the reference path passes through one, as long as some other path takes
+/// a different route. Alleles are the sequences strictly between the bubble's
+/// entrance and exit, in the reference path's orientation. Sites are sorted by
+/// position. Bubbles with more than `max_size` handles are ignored, and so are
+/// sites with an empty allele on a reference that has no bases to pad it with.
+pub fn deconstruct(
+    gfa: &flatgfa::FlatGFA,
+    ref_path: Id<Path>,
+    samples: &Samples,
+    max_size: usize,
+) -> Vec<Site> {
+    let bubbles = bubble::superbubbles(gfa, max_size);
+    let travs = bubble::traversals(gfa, &bubbles);
+
+    // The start offset of each step in the reference.
+    let ref_steps = &gfa.steps[gfa.paths[ref_path].steps];
+    let mut offsets = Vec::with_capacity(ref_steps.len());
+    let mut pos = 0;
+    for step in ref_steps {
+        offsets.push(pos);
+        pos += gfa.get_handle_seg(*step).len();
+    }
+
+    let mut sites = Vec::new();
+    for bubble_travs in travs.chunk_by(|a, b| a.bubble == b.bubble) {
+        for ref_trav in bubble_travs.iter().filter(|t| t.path == ref_path) {
+            let ref_walk = walk(gfa, ref_trav, false);
+            let mut walks = vec![ref_walk];
+            let mut genotypes: Vec<Vec<Option<usize>>> = samples
+                .haps
+                .iter()
+                .map(|haps| vec![None; haps.len()])
+                .collect();
+
+            // Collect the distinct walks and which haplotypes take each one.
+            for trav in bubble_travs {
+                let Some((s, h)) = samples.of_path[trav.path.index()] else {
+                    continue;
+                };
+                let w = walk(gfa, trav, trav.reverse != ref_trav.reverse);
+                let allele = match walks.iter().position(|x| *x == w) {
+                    Some(a) => a,
+                    None => {
+                        walks.push(w);
+                        walks.len() - 1
+                    }
+                };
+                genotypes[s][h].get_or_insert(allele);
+            }
+            if walks.len() == 1 {
+                continue;
+            }
+
+            let alleles: Vec<Vec<u8>> = walks
+                .iter()
+                .map(|w| {
+                    let mut seq = Vec::new();
+                    for handle in &w[1..w.len() - 1] {
+                        gfa.get_seq_oriented(*handle).extend_vec(&mut seq);
+                    }
+                    seq
+                })
+                .collect();
+            let padding = if alleles.iter().any(|a| a.is_empty()) {
+                let steps = &ref_trav.steps;
+                match padding(gfa, ref_steps, steps.start + 1, steps.end - 1) {
+                    Some(p) => Some(p),
+                    None => continue,
+                }
+            } else {
+                None
+            };
+            sites.push(Site {
+                pos: offsets[ref_trav.steps.start + 1],
+                depth: bubbles[ref_trav.bubble].depth,
+                entrance: walks[0][0],
+                exit: *walks[0].last().unwrap(),
+                alleles,
+                walks,
+                genotypes,
+                padding,
+            });
+        }
+    }
+    sites.sort_by_key(|site| site.pos);
+    sites
+}
+
+/// A VCF file describing variants against a reference path.
+///
+/// Like `vg deconstruct`, each record's ID names the bubble's entrance and exit
+/// and the `AT` field gives the walk for each allele. `LV` is the bubble's
+/// nesting depth. When any allele is empty, all the alleles are padded with the
+/// reference base just before the site (or after it, at the start of the
+/// reference), as VCF requires.
+pub struct Vcf<'a> {
+    pub gfa: &'a flatgfa::FlatGFA<'a>,
+    pub ref_path: Id<Path>,
+    pub samples: Samples,
+    pub sites: Vec<Site>,
+}
+
+impl Vcf<'_> {
+    /// Write a handle in the `>name` or `<name` style used in GFA walks.
+    fn write_handle(&self, f: &mut impl Write, handle: Handle) -> std::io::Result<()> {
+        let arrow = match handle.orient() {
+            Orientation::Forward => '>',
+            Orientation::Backward => '<',
+        };
+        let name = self.gfa.get_handle_seg(handle).name;
+        write!(f, "{arrow}{name}")
+    }
+}
+
+impl Emit for Vcf<'_> {
+    fn emit(self, f: &mut impl Write) -> std::io::Result<()> {
+        let ref_path = &self.gfa.paths[self.ref_path];
+        let chrom = self.gfa.get_path_name(ref_path);
+
+        writeln!(f, "##fileformat=VCFv4.2")?;
+        writeln!(
+            f,
+            "##INFO=<ID=AT,Number=R,Type=String,Description=\"Allele traversal as a path through the graph\">"
+        )?;
+        writeln!(
+            f,
+            "##INFO=<ID=LV,Number=1,Type=Integer,Description=\"Level in the snarl tree (0=top level)\">"
+        )?;
+        writeln!(
+            f,
+            "##FORMAT=<ID=GT,Number=1,Type=String,Description=\"Genotype\">"
+        )?;
+        writeln!(
+            f,
+            "##contig=<ID={},length={}>",
+            chrom,
+            path_len(self.gfa, ref_path)
+        )?;
+        write!(f, "#CHROM\tPOS\tID\tREF\tALT\tQUAL\tFILTER\tINFO\tFORMAT")?;
+        for name in &self.samples.names {
+            write!(f, "\t{name}")?;
+        }
+        writeln!(f)?;
+
+        for site in &self.sites {
+            // Pad all the alleles with the preceding base if any is empty.
+            let pos = match site.padding {
+                Some(Padding::Before(_)) => site.pos,
+                _ => site.pos + 1,
+            };
+
+            write!(f, "{chrom}\t{pos}\t")?;
+            self.write_handle(f, site.entrance)?;
+            self.write_handle(f, site.exit)?;
+            for (i, allele) in site.alleles.iter().enumerate() {
+                f.write_all(if i < 2 { b"\t" } else { b"," })?;
+                if let Some(Padding::Before(base)) = site.padding {
+                    f.write_all(&[base])?;
+                }
+                f.write_all(allele)?;
+                if let Some(Padding::After(base)) = site.padding {
+                    f.write_all(&[base])?;
+                }
+            }
+            write!(f, "\t60\t.\tAT=")?;
+            for (i, walk) in site.walks.iter().enumerate() {
+                if i > 0 {
+                    write!(f, ",")?;
+                }
+                for handle in walk {
+                    self.write_handle(f, *handle)?;
+                }
+            }
+            write!(f, ";LV={}\tGT", site.depth)?;
+
+            for haps in &site.genotypes {
+                let gt: Vec<String> = haps
+                    .iter()
+                    .map(|a| a.map_or(".".to_string(), |a| a.to_string()))
+                    .collect();
+                write!(f, "\t{}", gt.join("|"))?;
+            }
+            writeln!(f)?;
+        }
+        Ok(())
+    }
+}
+
+#[test]
+fn test_deconstruct() {
+    // A SNP (2 vs. 3) and a deletion (of 5), with two samples.
+    let gfa_text = b"S\t1\tAC\nS\t2\tG\nS\t3\tT\nS\t4\tCA\nS\t5\tTT\nS\t6\tG\n\
+        L\t1\t+\t2\t+\t0M\nL\t1\t+\t3\t+\t0M\nL\t2\t+\t4\t+\t0M\nL\t3\t+\t4\t+\t0M\n\
+        L\t4\t+\t5\t+\t0M\nL\t5\t+\t6\t+\t0M\nL\t4\t+\t6\t+\t0M\n\
+        P\tref\t1+,2+,4+,5+,6+\t*\nP\tA#1#c\t1+,3+,4+,6+\t*\n\
+        P\tA#2#c\t1+,2+,4+,5+,6+\t*\nP\tB#1#c\t6-,4-,3-,1-\t*\n";
+    let store = crate::parse::Parser::for_heap().parse_mem(gfa_text);
+    let gfa = store.as_ref();
+    let ref_path = gfa.find_path("ref".into()).unwrap();
+    let samples = Samples::new(&gfa, ref_path);
+    assert_eq!(samples.names, vec!["A", "B"]);
+
+    let sites = deconstruct(&gfa, ref_path, &samples, 100);
+    assert_eq!(sites.len(), 2);
+    assert_eq!(sites[0].pos, 2);
+    assert_eq!(sites[0].alleles, vec![b"G".to_vec(), b"T".to_vec()]);
+    assert_eq!(
+        sites[0].genotypes,
+        vec![vec![Some(1), Some(0)], vec![Some(1)]]
+    );
+    assert_eq!(sites[1].pos, 5);
+    assert_eq!(sites[1].alleles, vec![b"TT".to_vec(), b"".to_vec()]);
+    assert_eq!(sites[1].padding, Some(Padding::Before(b'A')));
+    assert_eq!(
+        sites[1].genotypes,
+        vec![vec![Some(1), Some(0)], vec![Some(1)]]
+    );
+
+    // The same deletion after an empty entrance segment: pad with the base
+    // before that. Without any bases before it, pad with the base after.
+    let gfa_text = b"S\t1\tA\nS\t2\t\nS\t5\tTT\nS\t6\tG\n\
+        L\t1\t+\t2\t+\t0M\nL\t2\t+\t5\t+\t0M\nL\t5\t+\t6\t+\t0M\nL\t2\t+\t6\t+\t0M\n\
+        P\tref\t1+,2+,5+,6+\t*\nP\tA#1#c\t1+,2+,6+\t*\nP\tx\t2+,5+,6+\t*\nP\tB#1#c\t2+,6+\t*\n";
+    let store = crate::parse::Parser::for_heap().parse_mem(gfa_text);
+    let gfa = store.as_ref();
+    let ref_path = gfa.find_path("ref".into()).unwrap();
+    let sites = deconstruct(&gfa, ref_path, &Samples::new(&gfa, ref_path), 100);
+    assert_eq!(sites[0].padding, Some(Padding::Before(b'A')));
+    let ref_path = gfa.find_path("x".into()).unwrap();
+    let sites = deconstruct(&gfa, ref_path, &Samples::new(&gfa, ref_path), 100);
+    assert_eq!(sites[0].padding, Some(Padding::After(b'G')));
+}
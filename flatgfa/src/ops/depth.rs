@@ -1,6 +1,7 @@
 use crate::emit::Emit;
 use crate::flatbed::HeapBEDStore;
 use crate::flatgfa;
+use crate::pansn::PathGroups;
 use crate::pool::Id;
 use bit_vec::BitVec;
 use std::io::Write;
@@ -55,6 +56,28 @@ pub fn seg_depth(gfa: &flatgfa::FlatGFA) -> Vec<usize> {
     depths
 }
 
+/// Compute the depth of each segment counting groups of paths instead of paths.
+///
+/// Each group (e.g., all the paths from one sample) counts at most once toward
+/// the depth of a segment, like the unique depth in `seg_depth_with_uniq`.
+pub fn group_seg_depth(gfa: &flatgfa::FlatGFA, groups: &PathGroups) -> Vec<usize> {
+    let mut depths = vec![0; gfa.segs.len()];
+    let mut seen = BitVec::from_elem(gfa.segs.len(), false);
+    for paths in &groups.paths {
+        seen.clear();
+        for &path in paths {
+            for step in gfa.get_path_steps(&gfa.paths[path]) {
+                let seg_id = step.segment().index();
+                if !seen[seg_id] {
+                    depths[seg_id] += 1;
+                    seen.set(seg_id, true);
+                }
+            }
+        }
+    }
+    depths
+}
+
 /// A printable segment depth table.
 ///
 /// Formats the result of `seg_depth_with_uniq` in an odgi-style TSV.
@@ -110,6 +133,36 @@ where
     (path_lengths, path_depths)
 }
 
+/// Compute the total length and mean depth of each *group* of paths.
+///
+/// This is like `path_depth`, but it aggregates over all the paths in a group
+/// (e.g., all the contigs for a sample). The mean is weighted by length, so it
+/// is the mean depth over all the group's bases.
+pub fn group_depth(gfa: &flatgfa::FlatGFA, groups: &PathGroups) -> (Vec<usize>, Vec<f64>) {
+    let seg_depths = seg_depth(gfa);
+    let mut lengths = Vec::with_capacity(groups.len());
+    let mut depths = Vec::with_capacity(groups.len());
+    for paths in &groups.paths {
+        let (mut total_len, mut total_depth) = (0, 0.0);
+        for &path in paths {
+            let (length, depth) = measure_path(gfa, path, &seg_depths);
+            if length > 0 {
+                total_len += length;
+                total_depth += depth * length as f64;
+            }
+        }
+        lengths.push(total_len);
+
+        // A group with no bases at all has depth 0.
+        depths.push(if total_len == 0 {
+            0.0
+        } else {
+            total_depth / total_len as f64
+        });
+    }
+    (lengths, depths)
+}
+
 /// Get a path's length (in base pairs) and average depth.
 ///
 /// Requires walking the path to measure its total length.
@@ -183,6 +236,31 @@ where
     }
 }
 
+/// A printable depth table for groups of paths.
+///
+/// Formats the result of `group_depth`, with one line per sample.
+pub struct GroupDepth<'a> {
+    pub groups: &'a PathGroups,
+    pub lengths: Vec<usize>,
+    pub depths: Vec<f64>,
+}
+
+impl Emit for GroupDepth<'_> {
+    fn emit(self, f: &mut impl Write) -> std::io::Result<()> {
+        writeln!(f, "#sample\tlength\tmean.depth")?;
+        for (idx, name) in self.groups.names.iter().enumerate() {
+            writeln!(
+                f,
+                "{}\t{}\t{}",
+                name,
+                self.lengths[idx],
+                format_float(self.depths[idx], 2),
+            )?;
+        }
+        Ok(())
+    }
+}
+
 /// Format an `f64` in an odgi-like way, with limited decimal digits and without
 /// trailing zeroes.
 ///
@@ -195,3 +273,19 @@ pub fn format_float(x: f64, digits: usize) -> String {
         .trim_end_matches('.')
         .to_string()
 }
+
+#[test]
+fn test_group_depth() {
+    use crate::pansn::GroupBy;
+
+    // Sample b's only path crosses an empty segment.
+    let gfa_text = b"S\t1\tAA\nS\t2\t\n\
+        P\ta#1#c\t1+\t*\nP\ta#2#c\t1+,2+\t*\nP\tb#1#c\t2+\t*\n";
+    let store = crate::parse::Parser::for_heap().parse_mem(gfa_text);
+    let gfa = store.as_ref();
+
+    let groups = PathGroups::new(&gfa, GroupBy::Sample);
+    let (lengths, depths) = group_depth(&gfa, &groups);
+    assert_eq!(lengths, vec![4, 0]);
+    assert_eq!(depths, vec![2.0, 0.0]);
+}
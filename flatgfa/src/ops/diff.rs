@@ -0,0 +1,564 @@
+use crate::emit::Emit;
+use crate::flatgfa::{self, Handle, Orientation, Path, Segment};
+use crate::ops::position::path_len;
+use crate::pool::Id;
+use bstr::{BStr, BString};
+use rayon::prelude::*;
+use std::collections::{HashMap, HashSet};
+use std::io::Write;
+use std::ops::Range;
+
+/// Give up on finding a minimal diff after this many edits.
+///
+/// Myers' algorithm takes quadratic space in the number of edits, so for very
+/// different sequences we fall back to reporting a single differing region.
+const MAX_EDITS: usize = 1000;
+
+/// How an entity differs between the two graphs.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Change {
+    Added,
+    Removed,
+    Changed,
+}
+
+impl Change {
+    fn symbol(self) -> char {
+        match self {
+            Change::Added => '+',
+            Change::Removed => '-',
+            Change::Changed => '~',
+        }
+    }
+}
+
+/// A segment that only appears in one graph, or that has a different name in
+/// each graph.
+pub struct SegDiff {
+    pub change: Change,
+
+    /// The segment's name in the first graph, if it appears there.
+    pub name_a: Option<usize>,
+
+    /// The segment's name in the second graph, if it appears there.
+    pub name_b: Option<usize>,
+
+    pub len: usize,
+}
+
+/// One end of a link, identified by segment name rather than ID.
+pub type LinkEnd = (usize, Orientation);
+
+/// A link that only appears in one graph.
+pub struct LinkDiff {
+    pub change: Change,
+    pub from: LinkEnd,
+    pub to: LinkEnd,
+}
+
+/// A path whose sequence differs, or that only appears in one graph.
+///
+/// For changed paths, `regions` lists the base-pair ranges in each graph's
+/// version of the path where the sequences diverge.
+pub struct PathDiff {
+    pub name: BString,
+    pub change: Change,
+    pub len_a: usize,
+    pub len_b: usize,
+    pub regions: Vec<(Range<usize>, Range<usize>)>,
+}
+
+/// All the differences between two graphs.
+pub struct GraphDiff {
+    pub segs: Vec<SegDiff>,
+    pub links: Vec<LinkDiff>,
+    pub paths: Vec<PathDiff>,
+}
+
+impl GraphDiff {
+    /// Check whether the graphs are equivalent.
+    pub fn is_empty(&self) -> bool {
+        self.segs.is_empty() && self.links.is_empty() && self.paths.is_empty()
+    }
+}
+
+/// Find the regions where two sequences differ, using Myers' O(ND) algorithm.
+///
+/// The result is a list of pairs of ranges: a region in `a` that is replaced by
+/// a region in `b`. Either range may be empty (for insertions and deletions).
+/// If the sequences need more than `max_edits` edits, we just report the single
+/// region between their common prefix and suffix.
+pub fn diff_regions<T: PartialEq>(
+    a: &[T],
+    b: &[T],
+    max_edits: usize,
+) -> Vec<(Range<usize>, Range<usize>)> {
+    // Trim the common prefix and suffix.
+    let pre = a.iter().zip(b).take_while(|(x, y)| x == y).count();
+    let (a_mid, b_mid) = (&a[pre..], &b[pre..]);
+    let suf = a_mid
+        .iter()
+        .rev()
+        .zip(b_mid.iter().rev())
+        .take_while(|(x, y)| x == y)
+        .count();
+    let (a_mid, b_mid) = (&a_mid[..a_mid.len() - suf], &b_mid[..b_mid.len() - suf]);
+    if a_mid.is_empty() && b_mid.is_empty() {
+        return Vec::new();
+    }
+    let (n, m) = (a_mid.len() as isize, b_mid.len() as isize);
+    let whole = vec![(pre..pre + a_mid.len(), pre..pre + b_mid.len())];
+
+    // The forward pass. `v[k + off]` is the furthest x reached on diagonal k.
+    // We save the relevant part of `v` before each round for the backtrace.
+    let limit = (n + m).min(max_edits as isize);
+    let off = limit + 1;
+    let mut v = vec![0isize; 2 * off as usize + 1];
+    let mut trace: Vec<Vec<isize>> = Vec::new();
+    let mut edits = None;
+    'search: for d in 0..=limit {
+        trace.push(v[(off - d - 1) as usize..=(off + d + 1) as usize].to_vec());
+        for k in (-d..=d).step_by(2) {
+            let down = k == -d || (k != d && v[(off + k - 1) as usize] < v[(off + k + 1) as usize]);
+            let mut x = if down {
+                v[(off + k + 1) as usize]
+            } else {
+                v[(off + k - 1) as usize] + 1
+            };
+            let mut y = x - k;
+            while x < n && y < m && a_mid[x as usize] == b_mid[y as usize] {
+                x += 1;
+                y += 1;
+            }
+            v[(off + k) as usize] = x;
+            if x >= n && y >= m {
+                edits = Some(d);
+                break 'search;
+            }
+        }
+    }
+    let Some(edits) = edits else {
+        return whole;
+    };
+
+    // Walk backward to find the matching "snakes," collecting the gaps between them.
+    let mut regions = Vec::new();
+    let (mut x, mut y) = (n, m);
+    let (mut gap_x, mut gap_y) = (n, m);
+    for d in (0..=edits).rev() {
+        // `trace[d]` covers diagonals -(d + 1) through d + 1.
+        let get = |k: isize| trace[d as usize][(k + d + 1) as usize];
+        let k = x - y;
+
+        // Find the previous point and where this round's edit landed.
+        let (prev, mid) = if d == 0 {
+            ((0, 0), (0, 0))
+        } else if k == -d || (k != d && get(k - 1) < get(k + 1)) {
+            let px = get(k + 1);
+            ((px, px - k - 1), (px, px - k))
+        } else {
+            let px = get(k - 1);
+            ((px, px - k + 1), (px + 1, px + 1 - k))
+        };
+
+        // The diagonal from `mid` to `(x, y)` matches; close the gap after it.
+        if mid.0 < x {
+            if (x, y) != (gap_x, gap_y) {
+                regions.push((x..gap_x, y..gap_y));
+            }
+            (gap_x, gap_y) = mid;
+        }
+        (x, y) = prev;
+    }
+    if (gap_x, gap_y) != (0, 0) {
+        regions.push((0..gap_x, 0..gap_y));
+    }
+
+    regions.reverse();
+    regions
+        .into_iter()
+        .map(|(ra, rb)| {
+            let shift = |r: Range<isize>| (r.start as usize + pre)..(r.end as usize + pre);
+            (shift(ra), shift(rb))
+        })
+        .collect()
+}
+
+/// Give every distinct oriented segment sequence, across both graphs, a number.
+///
+/// The result is a token for each handle in each graph, indexed by the handle's
+/// segment ID and orientation. Equal tokens mean equal sequences.
+fn seq_tokens(a: &flatgfa::FlatGFA, b: &flatgfa::FlatGFA) -> (Vec<[u32; 2]>, Vec<[u32; 2]>) {
+    let mut ids: HashMap<Vec<u8>, u32> = HashMap::new();
+    let mut tokens = |gfa: &flatgfa::FlatGFA| -> Vec<[u32; 2]> {
+        gfa.segs
+            .ids()
+            .map(|id| {
+                [Orientation::Forward, Orientation::Backward].map(|ori| {
+                    let seq = gfa.get_seq_oriented(id.handle(ori)).to_vec();
+                    let next = ids.len() as u32;
+                    *ids.entry(seq).or_insert(next)
+                })
+            })
+            .collect()
+    };
+    let tok_a = tokens(a);
+    let tok_b = tokens(b);
+    (tok_a, tok_b)
+}
+
+/// The token for a handle, from `seq_tokens`.
+fn handle_token(tokens: &[Vec<[u32; 2]>], graph: usize, handle: Handle) -> u32 {
+    tokens[graph][handle.segment().index()][u8::from(handle.orient()) as usize]
+}
+
+/// The nucleotide sequence of a whole path, and the start offset of each step
+/// (plus the total length at the end).
+fn path_seq(gfa: &flatgfa::FlatGFA, path: &Path) -> (Vec<u8>, Vec<usize>) {
+    let mut seq = Vec::new();
+    let mut offsets = vec![0];
+    for step in gfa.get_path_steps(path) {
+        gfa.get_seq_oriented(*step).extend_vec(&mut seq);
+        offsets.push(seq.len());
+    }
+    (seq, offsets)
+}
+
+/// Find the base-pair regions where two versions of a path differ.
+///
+/// We first align the paths step by step (comparing steps by sequence), and then
+/// refine each differing run of steps to the base level.
+fn path_regions(
+    (a, path_a): (&flatgfa::FlatGFA, &Path),
+    (b, path_b): (&flatgfa::FlatGFA, &Path),
+    tokens: &[Vec<[u32; 2]>],
+) -> Option<Vec<(Range<usize>, Range<usize>)>> {
+    let (seq_a, offs_a) = path_seq(a, path_a);
+    let (seq_b, offs_b) = path_seq(b, path_b);
+    if seq_a == seq_b {
+        return None;
+    }
+
+    let toks_a: Vec<u32> = a
+        .get_path_steps(path_a)
+        .map(|s| handle_token(tokens, 0, *s))
+        .collect();
+    let toks_b: Vec<u32> = b
+        .get_path_steps(path_b)
+        .map(|s| handle_token(tokens, 1, *s))
+        .collect();
+
+    let mut regions = Vec::new();
+    for (steps_a, steps_b) in diff_regions(&toks_a, &toks_b, MAX_EDITS) {
+        let bp_a = offs_a[steps_a.start]..offs_a[steps_a.end];
+        let bp_b = offs_b[steps_b.start]..offs_b[steps_b.end];
+        for (ra, rb) in diff_regions(&seq_a[bp_a.clone()], &seq_b[bp_b.clone()], MAX_EDITS) {
+            regions.push((
+                bp_a.start + ra.start..bp_a.start + ra.end,
+                bp_b.start + rb.start..bp_b.start + rb.end,
+            ));
+        }
+    }
+    Some(regions)
+}
+
+/// Pair up the segments in two graphs that have the same sequence.
+///
+/// Among segments with equal sequences, pairs with the same name come first;
+/// the rest pair up in graph order. The result gives, for each segment in `b`,
+/// the ID of its partner in `a`.
+fn match_segs(a: &flatgfa::FlatGFA, b: &flatgfa::FlatGFA) -> Vec<Option<Id<Segment>>> {
+    let mut by_seq: HashMap<&BStr, Vec<Id<Segment>>> = HashMap::new();
+    for (id, seg) in a.segs.items() {
+        by_seq.entry(a.get_seq(seg)).or_default().push(id);
+    }
+    let mut used = vec![false; a.segs.len()];
+    let mut partners = vec![None; b.segs.len()];
+
+    // First, segments with the same name and sequence.
+    let names_a: HashMap<usize, Id<Segment>> = a.segs.items().map(|(id, s)| (s.name, id)).collect();
+    for (id, seg) in b.segs.items() {
+        let name = seg.name;
+        if let Some(&other) = names_a.get(&name) {
+            if a.get_seq(&a.segs[other]) == b.get_seq(seg) {
+                used[other.index()] = true;
+                partners[id.index()] = Some(other);
+            }
+        }
+    }
+
+    // Then, the remaining segments with the same sequence, in order.
+    let mut next: HashMap<&BStr, usize> = HashMap::new();
+    for (id, seg) in b.segs.items() {
+        if partners[id.index()].is_some() {
+            continue;
+        }
+        let seq = b.get_seq(seg);
+        let Some(cands) = by_seq.get(seq) else {
+            continue;
+        };
+        let pos = next.entry(seq).or_default();
+        while *pos < cands.len() && used[cands[*pos].index()] {
+            *pos += 1;
+        }
+        if let Some(&other) = cands.get(*pos) {
+            used[other.index()] = true;
+            partners[id.index()] = Some(other);
+        }
+    }
+    partners
+}
+
+/// The links in a graph in a canonical orientation.
+///
+/// Each link is keyed by its endpoints, with segments given by `key` so that
+/// links from different graphs can be compared. The value has the endpoints
+/// with their segment names in this graph.
+fn keyed_links(
+    gfa: &flatgfa::FlatGFA,
+    key: impl Fn(Id<Segment>) -> usize,
+) -> HashMap<(LinkEnd, LinkEnd), (LinkEnd, LinkEnd)> {
+    let end = |h: Handle| (key(h.segment()), h.orient());
+    let name = |h: Handle| (gfa.get_handle_seg(h).name, h.orient());
+    gfa.links
+        .all()
+        .iter()
+        .map(|link| {
+            let (from, to) = (link.from, link.to);
+            let fwd = (end(from), end(to));
+            let rev = (end(to.flip()), end(from.flip()));
+            if rev < fwd {
+                (rev, (name(to.flip()), name(from.flip())))
+            } else {
+                (fwd, (name(from), name(to)))
+            }
+        })
+        .collect()
+}
+
+/// Compare two graphs.
+///
+/// Segments are matched by sequence (see `match_segs`), so a segment that was
+/// renamed counts as changed, and a segment whose sequence changed counts as
+/// removed and added. Links are matched by their endpoints, using the segment
+/// matching and treating a link and its reverse as the same. Paths are matched
+/// by name and compared by their sequence; for changed paths, we find the
+/// base-pair regions that differ. Paths are compared in parallel.
+pub fn diff(a: &flatgfa::FlatGFA, b: &flatgfa::FlatGFA) -> GraphDiff {
+    // Segments.
+    let partners = match_segs(a, b);
+    let mut matched = vec![false; a.segs.len()];
+    for other in partners.iter().flatten() {
+        matched[other.index()] = true;
+    }
+    let mut segs: Vec<SegDiff> = a
+        .segs
+        .items()
+        .filter(|(id, _)| !matched[id.index()])
+        .map(|(_, seg)| SegDiff {
+            change: Change::Removed,
+            name_a: Some(seg.name),
+            name_b: None,
+            len: seg.len(),
+        })
+        .collect();
+    for (id, seg) in b.segs.items() {
+        let other = partners[id.index()].map(|other| a.segs[other].name);
+        if other != Some(seg.name) {
+            segs.push(SegDiff {
+                change: if other.is_some() {
+                    Change::Changed
+                } else {
+                    Change::Added
+                },
+                name_a: other,
+                name_b: Some(seg.name),
+                len: seg.len(),
+            });
+        }
+    }
+
+    // Links. Segments only in `b` get keys past all of `a`'s segments.
+    let links_a = keyed_links(a, |id| id.index());
+    let links_b = keyed_links(b, |id| match partners[id.index()] {
+        Some(other) => other.index(),
+        None => a.segs.len() + id.index(),
+    });
+    let mut links: Vec<LinkDiff> = links_a
+        .iter()
+        .filter(|(key, _)| !links_b.contains_key(key))
+        .map(|(_, &ends)| (Change::Removed, ends))
+        .chain(
+            links_b
+                .iter()
+                .filter(|(key, _)| !links_a.contains_key(key))
+                .map(|(_, &ends)| (Change::Added, ends)),
+        )
+        .map(|(change, (from, to))| LinkDiff { change, from, to })
+        .collect();
+    links.sort_by_key(|l| (l.change != Change::Removed, l.from, l.to));
+
+    // Paths.
+    let (tok_a, tok_b) = seq_tokens(a, b);
+    let tokens = [tok_a, tok_b];
+    let paths_b: HashMap<&BStr, &Path> = b
+        .paths
+        .all()
+        .iter()
+        .map(|p| (b.get_path_name(p), p))
+        .collect();
+    let mut paths: Vec<PathDiff> = a
+        .paths
+        .all()
+        .par_iter()
+        .filter_map(|path_a| {
+            let name = a.get_path_name(path_a);
+            let len_a = path_len(a, path_a);
+            match paths_b.get(name) {
+                Some(path_b) => {
+                    let regions = path_regions((a, path_a), (b, path_b), &tokens)?;
+                    Some(PathDiff {
+                        name: name.into(),
+                        change: Change::Changed,
+                        len_a,
+                        len_b: path_len(b, path_b),
+                        regions,
+                    })
+                }
+                None => Some(PathDiff {
+                    name: name.into(),
+                    change: Change::Removed,
+                    len_a,
+                    len_b: 0,
+                    regions: Vec::new(),
+                }),
+            }
+        })
+        .collect();
+    let names_a: HashSet<&BStr> = a.paths.all().iter().map(|p| a.get_path_name(p)).collect();
+    for path in b.paths.all() {
+        let name = b.get_path_name(path);
+        if !names_a.contains(name) {
+            paths.push(PathDiff {
+                name: name.into(),
+                change: Change::Added,
+                len_a: 0,
+                len_b: path_len(b, path),
+                regions: Vec::new(),
+            });
+        }
+    }
+
+    GraphDiff { segs, links, paths }
+}
+
+impl Emit for GraphDiff {
+    /// Print the differences, one per line, GFA-style.
+    ///
+    /// Each line starts with `-` (only in the first graph), `+` (only in the
+    /// second), or `~` (changed), followed by a record type letter. Segment
+    /// lines give the segment's name and length, and renamed segments give
+    /// both names before the length; link lines give the
+    /// endpoints; changed path lines give one differing region per line, as
+    /// half-open ranges in the first and second graphs.
+    fn emit(self, f: &mut impl Write) -> std::io::Result<()> {
+        for seg in self.segs {
+            let sym = seg.change.symbol();
+            match (seg.name_a, seg.name_b) {
+                (Some(a), Some(b)) => writeln!(f, "{sym}\tS\t{a}\t{b}\t{}", seg.len)?,
+                (Some(name), None) | (None, Some(name)) => {
+                    writeln!(f, "{sym}\tS\t{name}\t{}", seg.len)?
+                }
+                (None, None) => unreachable!(),
+            }
+        }
+        for link in self.links {
+            writeln!(
+                f,
+                "{}\tL\t{}\t{}\t{}\t{}",
+                link.change.symbol(),
+                link.from.0,
+                link.from.1,
+                link.to.0,
+                link.to.1,
+            )?;
+        }
+        for path in self.paths {
+            let sym = path.change.symbol();
+            match path.change {
+                Change::Added => writeln!(f, "{sym}\tP\t{}\t{}", path.name, path.len_b)?,
+                Change::Removed => writeln!(f, "{sym}\tP\t{}\t{}", path.name, path.len_a)?,
+                Change::Changed => {
+                    for (ra, rb) in path.regions {
+                        writeln!(
+                            f,
+                            "{sym}\tP\t{}\t{}\t{}\t{}\t{}",
+                            path.name, ra.start, ra.end, rb.start, rb.end
+                        )?;
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+#[test]
+fn test_diff_regions() {
+    assert!(diff_regions(b"ACGT", b"ACGT", 10).is_empty());
+    assert_eq!(
+        diff_regions(b"ACGTACGT", b"ACTTACGT", 10),
+        vec![(2..3, 2..3)]
+    );
+    assert_eq!(
+        diff_regions(b"AAACCCGGGTTT", b"AAACGGGTTTT", 10),
+        vec![(4..6, 4..4), (9..9, 7..8)]
+    );
+    assert_eq!(diff_regions(b"ABCDEF", b"AXCDEY", 1), vec![(1..6, 1..6)]);
+}
+
+#[test]
+fn test_diff() {
+    // The second graph renumbers the segments, changes one sequence, and adds a
+    // segment to path y.
+    let a = crate::parse::Parser::for_heap().parse_mem(
+        b"S\t1\tAC\nS\t2\tGT\nS\t3\tAAA\nL\t1\t+\t2\t+\t0M\nL\t2\t+\t3\t-\t0M\n\
+        P\tx\t1+,2+\t*\nP\ty\t2+,3-\t*\n",
+    );
+    let b = crate::parse::Parser::for_heap().parse_mem(
+        b"S\t5\tGT\nS\t7\tAC\nS\t3\tCCC\nS\t8\tG\n\
+        L\t7\t+\t5\t+\t0M\nL\t3\t+\t5\t-\t0M\nL\t3\t+\t8\t+\t0M\n\
+        P\tx\t7+,5+\t*\nP\ty\t5+,3+,8+\t*\n",
+    );
+    let d = diff(&a.as_ref(), &b.as_ref());
+
+    let segs: Vec<_> = d
+        .segs
+        .iter()
+        .map(|s| (s.change, s.name_a, s.name_b))
+        .collect();
+    assert_eq!(
+        segs,
+        vec![
+            (Change::Removed, Some(3), None),
+            (Change::Changed, Some(2), Some(5)),
+            (Change::Changed, Some(1), Some(7)),
+            (Change::Added, None, Some(3)),
+            (Change::Added, None, Some(8)),
+        ]
+    );
+
+    // The renamed link 1+ -> 2+ is unchanged, but the ones touching 3 are new.
+    let links: Vec<_> = d
+        .links
+        .iter()
+        .map(|l| (l.change, l.from.0, l.to.0))
+        .collect();
+    assert_eq!(links.len(), 3);
+    assert!(links.contains(&(Change::Removed, 2, 3)));
+    assert!(!d.links.iter().any(|l| l.from.0 == 7 || l.to.0 == 7));
+
+    // Path x spells the same sequence; y differs.
+    assert_eq!(d.paths.len(), 1);
+    assert_eq!(d.paths[0].name, "y");
+}
@@ -0,0 +1,138 @@
+use crate::emit::Emit;
+use crate::flatgfa::{self, Handle, Orientation};
+use bstr::ByteSlice;
+use std::io::Write;
+
+/// Colors for path traversals. (This is ColorBrewer's "Dark2" scheme.)
+const PATH_COLORS: [&str; 8] = [
+    "#1b9e77", "#d95f02", "#7570b3", "#e7298a", "#66a61e", "#e6ab02", "#a6761d", "#666666",
+];
+
+/// Show at most this many bases of each segment's sequence.
+const MAX_SEQ: usize = 40;
+
+/// Escape a string for use in a quoted DOT label.
+fn escape(s: &[u8]) -> String {
+    let mut out = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            _ => out.push(c),
+        }
+    }
+    out
+}
+
+/// A graph in Graphviz's DOT language.
+///
+/// Each segment is a box labeled with its name and length (and optionally its
+/// sequence). Links become edges that leave and enter the side of the box that
+/// matches their orientation: the right side is the forward end. Paths can
+/// optionally be drawn as colored edges between their consecutive steps.
+pub struct Dot<'a> {
+    pub gfa: &'a flatgfa::FlatGFA<'a>,
+
+    /// Include each segment's sequence in its label.
+    pub show_seq: bool,
+
+    /// Draw every path's traversal.
+    pub show_paths: bool,
+}
+
+impl Dot<'_> {
+    /// Write an edge between two handles, with ports for their orientations.
+    fn write_edge(
+        &self,
+        f: &mut impl Write,
+        from: Handle,
+        to: Handle,
+        attrs: &str,
+    ) -> std::io::Result<()> {
+        let tail = match from.orient() {
+            Orientation::Forward => "e",
+            Orientation::Backward => "w",
+        };
+        let head = match to.orient() {
+            Orientation::Forward => "w",
+            Orientation::Backward => "e",
+        };
+        let from_name = self.gfa.get_handle_seg(from).name;
+        let to_name = self.gfa.get_handle_seg(to).name;
+        write!(f, "  \"{from_name}\":{tail} -> \"{to_name}\":{head}")?;
+        if attrs.is_empty() {
+            writeln!(f, ";")
+        } else {
+            writeln!(f, " [{attrs}];")
+        }
+    }
+}
+
+impl Emit for Dot<'_> {
+    fn emit(self, f: &mut impl Write) -> std::io::Result<()> {
+        writeln!(f, "digraph {{")?;
+        writeln!(f, "  rankdir=LR;")?;
+        writeln!(f, "  node [shape=box];")?;
+
+        for seg in self.gfa.segs.all() {
+            let name = seg.name;
+            write!(f, "  \"{}\" [label=\"{}\\n{} bp", name, name, seg.len())?;
+            if self.show_seq {
+                let seq = self.gfa.get_seq(seg);
+                write!(f, "\\n")?;
+                if seq.len() > MAX_SEQ {
+                    write!(f, "{}...", seq[..MAX_SEQ].as_bstr())?;
+                } else {
+                    write!(f, "{seq}")?;
+                }
+            }
+            writeln!(f, "\"];")?;
+        }
+
+        for link in self.gfa.links.all() {
+            self.write_edge(f, link.from, link.to, "")?;
+        }
+
+        if self.show_paths {
+            for (i, path) in self.gfa.paths.all().iter().enumerate() {
+                let color = PATH_COLORS[i % PATH_COLORS.len()];
+                let name = escape(self.gfa.get_path_name(path));
+                let steps = &self.gfa.steps[path.steps];
+                for (j, pair) in steps.windows(2).enumerate() {
+                    // Label only the first edge in each path.
+                    let attrs = if j == 0 {
+                        format!("color=\"{color}\", fontcolor=\"{color}\", penwidth=2, label=\"{name}\"")
+                    } else {
+                        format!("color=\"{color}\", penwidth=2")
+                    };
+                    self.write_edge(f, pair[0], pair[1], &attrs)?;
+                }
+            }
+        }
+
+        writeln!(f, "}}")
+    }
+}
+
+#[test]
+fn test_dot() {
+    let gfa_text = b"S\t1\tACGT\nS\t2\tGG\nL\t1\t+\t2\t-\t0M\nP\tx\t1+,2-\t*\n";
+    let store = crate::parse::Parser::for_heap().parse_mem(gfa_text);
+    let gfa = store.as_ref();
+    let mut buf = Vec::new();
+    Dot {
+        gfa: &gfa,
+        show_seq: true,
+        show_paths: true,
+    }
+    .emit(&mut buf)
+    .unwrap();
+    let dot = String::from_utf8(buf).unwrap();
+    assert!(dot.contains("  \"1\" [label=\"1\\n4 bp\\nACGT\"];\n"));
+    assert!(dot.contains("  \"1\":e -> \"2\":e;\n"));
+    assert!(dot.contains("label=\"x\""));
+
+    assert_eq!(escape(b"a\"b\\c\nd"), "a\\\"b\\\\c\\nd");
+}
@@ -1,12 +1,24 @@
-use crate::flatgfa::{self, Handle, Segment};
+use crate::flatgfa::{self, Handle, Path, Segment};
+use crate::ops::position;
 use crate::pool::{self, Id, Span, Store};
-use std::collections::HashMap;
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, HashMap};
+use std::ops::Range;
+
+/// By default, merge subpaths that are at most this many base pairs apart.
+pub const MAX_DISTANCE_SUBPATHS: usize = 300000;
+
+/// By default, stop merging subpaths after this many iterations.
+pub const MAX_MERGING_ITERATIONS: usize = 6;
 
 /// A helper to construct a new graph that includes part of an old graph.
 pub struct SubgraphBuilder<'a> {
     pub old: &'a flatgfa::FlatGFA<'a>,
     pub store: flatgfa::HeapGFAStore,
     pub seg_map: HashMap<Id<Segment>, Id<Segment>>,
+
+    /// Name subpaths `name[start-end]`, as in PanSN, instead of `name:start-end`.
+    pub bracket_names: bool,
 }
 
 pub struct SubpathStart {
@@ -20,6 +32,7 @@ impl<'a> SubgraphBuilder<'a> {
             old,
             store: flatgfa::HeapGFAStore::default(),
             seg_map: HashMap::new(),
+            bracket_names: false,
         }
     }
 
@@ -35,12 +48,16 @@ impl<'a> SubgraphBuilder<'a> {
 
     /// Add a segment from the source graph to this subgraph.
     fn include_seg(&mut self, seg_id: Id<Segment>) {
+        let name = self.old.segs[seg_id].name;
+        self.include_seg_named(seg_id, name);
+    }
+
+    /// Add a segment from the source graph to this subgraph under a new name.
+    fn include_seg_named(&mut self, seg_id: Id<Segment>, name: usize) {
         let seg = &self.old.segs[seg_id];
-        let new_seg_id = self.store.add_seg(
-            seg.name,
-            self.old.get_seq(seg),
-            self.old.get_optional_data(seg),
-        );
+        let new_seg_id =
+            self.store
+                .add_seg(name, self.old.get_seq(seg), self.old.get_optional_data(seg));
         self.seg_map.insert(seg_id, new_seg_id);
     }
 
@@ -55,7 +72,12 @@ impl<'a> SubgraphBuilder<'a> {
     /// Add a single subpath from the given path to the subgraph.
     fn include_subpath(&mut self, path: &flatgfa::Path, start: &SubpathStart, end_pos: usize) {
         let steps = pool::Span::new(start.step, self.store.steps.next_id()); // why the next id?
-        let name = format!("{}:{}-{}", self.old.get_path_name(path), start.pos, end_pos);
+        let name = self.old.get_path_name(path);
+        let name = if self.bracket_names {
+            bracket_name(name, start.pos, end_pos)
+        } else {
+            format!("{}:{}-{}", name, start.pos, end_pos)
+        };
         self.store
             .add_path(name.as_bytes(), steps, std::iter::empty());
     }
@@ -144,24 +166,58 @@ impl<'a> SubgraphBuilder<'a> {
         self.seg_map.contains_key(&old_seg_id)
     }
 
-    /// Extract a subgraph consisting of a neighborhood of segments up to `dist` links away
-    /// from the given segment in the original graph.
+    /// Add an entire path from the source graph to the subgraph, keeping its name.
+    fn include_path(&mut self, path: &flatgfa::Path) {
+        let steps: Vec<Handle> = self.old.steps[path.steps]
+            .iter()
+            .map(|step| self.tr_handle(*step))
+            .collect();
+        let steps = self.store.add_steps(steps.into_iter());
+        let overlaps: Vec<Vec<flatgfa::AlignOp>> = self.old.overlaps[path.overlaps]
+            .iter()
+            .map(|span| self.old.get_alignment(*span).ops.to_vec())
+            .collect();
+        self.store
+            .add_path(self.old.get_path_name(path), steps, overlaps.into_iter());
+    }
+
+    /// Extract a subgraph consisting of exactly the given segments.
     ///
-    /// Include any links between the segments in the neighborhood and subpaths crossing
-    /// through the neighborhood.
-    pub fn extract(
-        &mut self,
-        origin: Id<Segment>,
-        dist: usize,
-        max_distance_subpaths: usize,
-        num_iterations: usize,
-    ) {
-        self.include_seg(origin);
+    /// Include any links between the segments. Paths that stay entirely within the
+    /// subgraph are included whole, under their original names; other paths contribute
+    /// the subpaths that cross through it. With `renumber`, the segments get new names
+    /// (1, 2, 3, ...) in the order given.
+    pub fn extract_segs(&mut self, segs: &[Id<Segment>], renumber: bool) {
+        for (idx, &seg_id) in segs.iter().enumerate() {
+            if renumber {
+                self.include_seg_named(seg_id, idx + 1);
+            } else {
+                self.include_seg(seg_id);
+            }
+        }
 
-        // Find the set of all segments that are c links away.
-        let mut frontier: Vec<Id<Segment>> = Vec::new();
+        for link in self.old.links.all().iter() {
+            if self.contains(link.from.segment()) && self.contains(link.to.segment()) {
+                self.include_link(link);
+            }
+        }
+
+        for path in self.old.paths.all().iter() {
+            let steps = &self.old.steps[path.steps];
+            if steps.iter().all(|step| self.contains(step.segment())) {
+                if !steps.is_empty() {
+                    self.include_path(path);
+                }
+            } else {
+                self.find_subpaths(path);
+            }
+        }
+    }
+
+    /// Expand the subgraph to include all segments up to `dist` links away from the
+    /// segments in `frontier`.
+    fn expand(&mut self, mut frontier: Vec<Id<Segment>>, dist: usize) {
         let mut next_frontier: Vec<Id<Segment>> = Vec::new();
-        frontier.push(origin);
         for _ in 0..dist {
             while let Some(seg_id) = frontier.pop() {
                 for link in self.old.links.all().iter() {
@@ -176,6 +232,37 @@ impl<'a> SubgraphBuilder<'a> {
             }
             (frontier, next_frontier) = (next_frontier, frontier);
         }
+    }
+
+    /// Finish the subgraph by including all the links and subpaths within it.
+    fn finish(&mut self) {
+        for link in self.old.links.all().iter() {
+            if self.contains(link.from.segment()) && self.contains(link.to.segment()) {
+                self.include_link(link);
+            }
+        }
+
+        for path in self.old.paths.all().iter() {
+            self.find_subpaths(path);
+        }
+    }
+
+    /// Extract a subgraph consisting of a neighborhood of segments up to `dist` links away
+    /// from the given segment in the original graph.
+    ///
+    /// Include any links between the segments in the neighborhood and subpaths crossing
+    /// through the neighborhood.
+    pub fn extract(
+        &mut self,
+        origin: Id<Segment>,
+        dist: usize,
+        max_distance_subpaths: usize,
+        num_iterations: usize,
+    ) {
+        self.include_seg(origin);
+
+        // Find the set of all segments that are c links away.
+        self.expand(vec![origin], dist);
 
         // Merge subpaths within max_distance_subpaths bp of each other, num_iterations times
         for _ in 0..num_iterations {
@@ -184,16 +271,167 @@ impl<'a> SubgraphBuilder<'a> {
             }
         }
 
-        // Include all links within the subgraph.
-        for link in self.old.links.all().iter() {
-            if self.contains(link.from.segment()) && self.contains(link.to.segment()) {
-                self.include_link(link);
+        self.finish();
+    }
+
+    /// Extract a subgraph around base-pair ranges of paths.
+    ///
+    /// Include every segment that a path traverses within one of its ranges, plus the
+    /// neighborhood up to `dist` links away. Then include links and subpaths as in
+    /// `extract`.
+    pub fn extract_ranges(&mut self, ranges: &[(Id<Path>, Range<usize>)], dist: usize) {
+        let mut frontier = Vec::new();
+        for (path_id, range) in ranges {
+            let path = &self.old.paths[*path_id];
+            let Some((step, pos)) = position::step_at(self.old, path, range.start) else {
+                continue;
+            };
+            let start = SubpathStart { step, pos };
+
+            // Walk from the step containing the start until we pass the end.
+            let mut pos = start.pos;
+            for step in &self.old.steps[Span::new(start.step, path.steps.end)] {
+                if pos >= range.end {
+                    break;
+                }
+                if !self.contains(step.segment()) {
+                    self.include_seg(step.segment());
+                    frontier.push(step.segment());
+                }
+                pos += self.old.get_handle_seg(*step).len();
             }
         }
+        self.expand(frontier, dist);
+        self.finish();
+    }
 
-        // Find subpaths within the subgraph.
-        for path in self.old.paths.all().iter() {
-            self.find_subpaths(path);
+    /// Extract a subgraph consisting of the segments within `max_bp` base pairs of any
+    /// of the `origins`.
+    ///
+    /// The distance to a segment is the number of base pairs between it and the
+    /// nearest origin, not counting the segments at either end; so the origins and
+    /// their direct neighbors always have distance 0. We find distances with
+    /// Dijkstra's algorithm over oriented handles, following links in either
+    /// direction. Subpaths are named `name[start-end]`, and a path whose name
+    /// already has a `[start-end]` suffix gets coordinates relative to its start.
+    pub fn extract_bp(&mut self, origins: &[Id<Segment>], max_bp: usize) {
+        // Build an adjacency list over handles.
+        let mut succs: HashMap<Handle, Vec<Handle>> = HashMap::new();
+        for link in self.old.links.all() {
+            succs.entry(link.from).or_default().push(link.to);
+            succs
+                .entry(link.to.flip())
+                .or_default()
+                .push(link.from.flip());
+        }
+
+        // The distance we track for each handle is to its *far* end.
+        let mut dist: HashMap<Handle, usize> = HashMap::new();
+        let mut queue = BinaryHeap::new();
+        for &origin in origins {
+            if !self.contains(origin) {
+                self.include_seg(origin);
+            }
+            for orient in [
+                flatgfa::Orientation::Forward,
+                flatgfa::Orientation::Backward,
+            ] {
+                let handle = origin.handle(orient);
+                dist.insert(handle, 0);
+                queue.push(Reverse((0, handle)));
+            }
+        }
+
+        while let Some(Reverse((d, handle))) = queue.pop() {
+            if dist.get(&handle).is_some_and(|&best| best < d) {
+                continue; // A stale entry.
+            }
+            for &next in succs.get(&handle).into_iter().flatten() {
+                if !self.contains(next.segment()) {
+                    self.include_seg(next.segment());
+                }
+                let next_d = d + self.old.get_handle_seg(next).len();
+                if next_d <= max_bp && dist.get(&next).is_none_or(|&best| next_d < best) {
+                    dist.insert(next, next_d);
+                    queue.push(Reverse((next_d, next)));
+                }
+            }
+        }
+
+        self.bracket_names = true;
+        self.finish();
+    }
+}
+
+/// Name a subpath in PanSN style: `name[start-end]`. If the name already has a
+/// `[start-end]` (or `[start]`) suffix, the new coordinates are offset by its start.
+fn bracket_name(name: &bstr::BStr, start: usize, end: usize) -> String {
+    let name = name.to_string();
+    if let Some(open) = name.strip_suffix(']').and_then(|n| n.rfind('[')) {
+        let coords = &name[open + 1..name.len() - 1];
+        let offset = coords
+            .split('-')
+            .next()
+            .and_then(|s| s.parse::<usize>().ok());
+        if let Some(offset) = offset {
+            return format!("{}[{}-{}]", &name[..open], offset + start, offset + end);
         }
     }
+    format!("{name}[{start}-{end}]")
+}
+
+#[test]
+fn test_extract_ranges() {
+    // A chain of four 2-bp segments, with a bubble at 2/3.
+    let gfa_text = b"S\t1\tAA\nS\t2\tCC\nS\t3\tGG\nS\t4\tTT\n\
+        L\t1\t+\t2\t+\t0M\nL\t1\t+\t3\t+\t0M\nL\t2\t+\t4\t+\t0M\nL\t3\t+\t4\t+\t0M\n\
+        P\tx\t1+,2+,4+\t*\nP\ty\t1+,3+,4+\t*\n";
+    let store = crate::parse::Parser::for_heap().parse_mem(gfa_text);
+    let gfa = store.as_ref();
+    let x = gfa.find_path("x".into()).unwrap();
+
+    // Bases 1-3 of x touch segments 1 and 2.
+    let mut subgraph = SubgraphBuilder::new(&gfa);
+    subgraph.extract_ranges(&[(x, 1..3)], 0);
+    let sub = subgraph.store.as_ref();
+    assert_eq!(sub.segs.len(), 2);
+    assert_eq!(sub.links.len(), 1);
+    let names: Vec<_> = sub
+        .paths
+        .all()
+        .iter()
+        .map(|p| sub.get_path_name(p).to_string())
+        .collect();
+    assert_eq!(names, vec!["x:0-4", "y:0-2"]);
+
+    // One link away brings in segments 3 and 4 as well.
+    let mut subgraph = SubgraphBuilder::new(&gfa);
+    subgraph.extract_ranges(&[(x, 1..3)], 1);
+    assert_eq!(subgraph.store.as_ref().segs.len(), 4);
+}
+
+#[test]
+fn test_extract_bp() {
+    // A chain of segments with lengths 1, 100, 1, 1: the long one is a barrier.
+    let long = "C".repeat(100);
+    let gfa_text = format!(
+        "S\t1\tA\nS\t2\t{long}\nS\t3\tG\nS\t4\tT\nS\t5\tA\n\
+        L\t1\t+\t2\t+\t0M\nL\t2\t+\t3\t+\t0M\nL\t3\t+\t4\t-\t0M\n\
+        P\tx#1#chr1[10]\t1+,2+,3+,4-\t*\n"
+    );
+    let store = crate::parse::Parser::for_heap().parse_mem(gfa_text.as_bytes());
+    let gfa = store.as_ref();
+    let seg = |name| gfa.find_seg(name).unwrap();
+
+    // From segment 4, segment 3 is adjacent and 2 is 1 bp away; 1 is 101 bp away.
+    let mut subgraph = SubgraphBuilder::new(&gfa);
+    subgraph.extract_bp(&[seg(4)], 50);
+    let sub = subgraph.store.as_ref();
+    assert_eq!(sub.segs.len(), 3);
+    assert_eq!(sub.get_path_name(&sub.paths.all()[0]), "x#1#chr1[11-113]");
+
+    // Two origins, and segment 5 is disconnected.
+    let mut subgraph = SubgraphBuilder::new(&gfa);
+    subgraph.extract_bp(&[seg(1), seg(5)], 0);
+    assert_eq!(subgraph.store.as_ref().segs.len(), 3);
 }
@@ -0,0 +1,123 @@
+use crate::flatgfa::{self, Path};
+use crate::ops::position::{path_len, PathRange};
+use crate::pool::Id;
+use rayon::prelude::*;
+use std::io::Write;
+use std::ops::Range;
+
+/// A path, or a base-pair range within a path, to export as a sequence.
+pub struct PathSelection {
+    pub path: Id<Path>,
+    pub range: Option<Range<usize>>,
+}
+
+impl PathSelection {
+    /// Select every path in the graph, in its entirety.
+    pub fn all(gfa: &flatgfa::FlatGFA) -> Vec<Self> {
+        gfa.paths
+            .ids()
+            .map(|path| Self { path, range: None })
+            .collect()
+    }
+
+    /// Look up the paths named by a list of `name` or `name:start-end` specs.
+    pub fn find(gfa: &flatgfa::FlatGFA, specs: &[PathRange]) -> Result<Vec<Self>, &'static str> {
+        specs
+            .iter()
+            .map(|spec| {
+                let path = gfa.find_path(spec.name()).ok_or("path not found")?;
+                Ok(Self {
+                    path,
+                    range: spec.range.clone(),
+                })
+            })
+            .collect()
+    }
+}
+
+/// Append the nucleotide sequence for a range of a path to a buffer.
+///
+/// The range is in base pairs along the path. Steps that fall entirely outside
+/// the range are skipped, and the steps at either end are trimmed.
+pub fn path_seq_into(
+    gfa: &flatgfa::FlatGFA,
+    path: &flatgfa::Path,
+    range: Range<usize>,
+    buf: &mut Vec<u8>,
+) {
+    let mut pos = 0;
+    for step in gfa.get_path_steps(path) {
+        if pos >= range.end {
+            break;
+        }
+        let seq = gfa.get_seq_oriented(*step);
+        let end = pos + seq.len();
+        if end > range.start {
+            let lo = range.start.max(pos) - pos;
+            let hi = range.end.min(end) - pos;
+            seq.slice(lo..hi).extend_vec(buf);
+        }
+        pos = end;
+    }
+}
+
+/// Format a single FASTA record for a selected path.
+///
+/// Sequence lines are wrapped at `width` characters, or not at all if `width`
+/// is zero. Subranges get a `name:start-end` header, like `samtools faidx`.
+fn fasta_record(gfa: &flatgfa::FlatGFA, sel: &PathSelection, width: usize) -> Vec<u8> {
+    let path = &gfa.paths[sel.path];
+    let name = gfa.get_path_name(path);
+    let len = path_len(gfa, path);
+
+    // Write the header line.
+    let mut out = Vec::new();
+    let range = match &sel.range {
+        Some(range) => {
+            let range = range.start.min(len)..range.end.min(len);
+            writeln!(out, ">{}:{}-{}", name, range.start, range.end).unwrap();
+            range
+        }
+        None => {
+            writeln!(out, ">{name}").unwrap();
+            0..len
+        }
+    };
+
+    // Write the sequence, wrapped to the requested width.
+    let mut seq = Vec::with_capacity(range.len());
+    path_seq_into(gfa, path, range, &mut seq);
+    if width == 0 {
+        out.extend_from_slice(&seq);
+        out.push(b'\n');
+    } else {
+        for line in seq.chunks(width) {
+            out.extend_from_slice(line);
+            out.push(b'\n');
+        }
+    }
+    out
+}
+
+/// Write the sequences of the selected paths as FASTA records.
+///
+/// Records are formatted in parallel, a batch at a time, and written in the
+/// order of `selections`.
+pub fn paths_to_fasta(
+    gfa: &flatgfa::FlatGFA,
+    selections: &[PathSelection],
+    width: usize,
+    f: &mut impl Write,
+) -> std::io::Result<()> {
+    let batch_size = rayon::current_num_threads().max(1);
+    for batch in selections.chunks(batch_size) {
+        let records: Vec<Vec<u8>> = batch
+            .par_iter()
+            .map(|sel| fasta_record(gfa, sel, width))
+            .collect();
+        for record in records {
+            f.write_all(&record)?;
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,286 @@
+use crate::emit::Emit;
+use crate::flatgfa::{self, Handle, Orientation, Path, Sequence};
+use crate::ops::bubble::HandleGraph;
+use crate::ops::fasta::path_seq_into;
+use crate::pool::Id;
+use crate::print::Display;
+use memchr::memmem;
+use rayon::prelude::*;
+use std::io::Write;
+use std::ops::Range;
+
+/// An occurrence of the query that follows a walk through the graph.
+pub struct GraphMatch {
+    /// The handles that spell out the query, in order.
+    pub walk: Vec<Handle>,
+
+    /// The offset where the match starts in the first handle's sequence.
+    pub start: usize,
+
+    /// The offset where the match ends (exclusive) in the last handle's sequence.
+    pub end: usize,
+}
+
+/// An occurrence of the query (or its reverse complement) in a path's sequence.
+pub struct PathMatch {
+    pub path: Id<Path>,
+
+    /// The base-pair range of the match in the path.
+    pub range: Range<usize>,
+
+    /// Whether the path contains the query's reverse complement here.
+    pub reverse: bool,
+
+    /// The range of steps in the path that the match touches.
+    pub steps: Range<usize>,
+
+    /// The offset where the match starts in the first step's handle.
+    pub offset: usize,
+}
+
+/// Get the reverse complement of a sequence.
+fn revcmp(seq: &[u8]) -> Vec<u8> {
+    Sequence::new(seq, Orientation::Backward).to_vec()
+}
+
+/// Continue matching the rest of the query, `rest`, at `offset` in the last
+/// handle of `walk`, following links into other handles as needed. Empty
+/// segments end the walk (so we can't get stuck in a cycle of them).
+fn extend(
+    gfa: &flatgfa::FlatGFA,
+    graph: &HandleGraph,
+    walk: &mut Vec<Handle>,
+    start: usize,
+    offset: usize,
+    rest: &[u8],
+    out: &mut Vec<GraphMatch>,
+) {
+    let handle = *walk.last().unwrap();
+    let seq = gfa.get_seq_oriented(handle);
+    let avail = (seq.len() - offset).min(rest.len());
+    if !(0..avail).all(|i| seq.index(offset + i).eq_ignore_ascii_case(&rest[i])) {
+        return;
+    }
+    if avail == rest.len() {
+        out.push(GraphMatch {
+            walk: walk.clone(),
+            start,
+            end: offset + avail,
+        });
+        return;
+    }
+    for &next in graph.succs(handle) {
+        if gfa.get_handle_seg(next).len() == 0 {
+            continue;
+        }
+        walk.push(next);
+        extend(gfa, graph, walk, start, 0, &rest[avail..], out);
+        walk.pop();
+    }
+}
+
+/// Find every walk in the graph that spells out the query.
+///
+/// We search both orientations of every segment, so this finds occurrences of
+/// the query on either strand: a match on a backward handle is an occurrence of
+/// the query's reverse complement in the segment's sequence. Matches can span
+/// any number of segments, following links. (A query that is its own reverse
+/// complement matches each place twice, once in each direction.) Matching ignores
+/// case.
+pub fn find_in_graph(gfa: &flatgfa::FlatGFA, query: &[u8]) -> Vec<GraphMatch> {
+    if query.is_empty() {
+        return Vec::new();
+    }
+    let graph = HandleGraph::new(gfa);
+    gfa.segs
+        .ids()
+        .flat_map(|id| [Orientation::Forward, Orientation::Backward].map(|o| id.handle(o)))
+        .collect::<Vec<_>>()
+        .into_par_iter()
+        .flat_map_iter(|handle| {
+            let mut out = Vec::new();
+            let len = gfa.get_handle_seg(handle).len();
+            let mut walk = vec![handle];
+            for offset in 0..len {
+                extend(gfa, &graph, &mut walk, offset, offset, query, &mut out);
+            }
+            out
+        })
+        .collect()
+}
+
+/// Find every occurrence of the query, and its reverse complement, in each path.
+///
+/// Matches may overlap. A query that is its own reverse complement is only
+/// reported once per occurrence. Matching ignores case.
+pub fn find_in_paths(gfa: &flatgfa::FlatGFA, query: &[u8]) -> Vec<PathMatch> {
+    if query.is_empty() {
+        return Vec::new();
+    }
+    let fwd = query.to_ascii_uppercase();
+    let rev = revcmp(&fwd);
+    let mut finders = vec![(memmem::Finder::new(&fwd), false)];
+    if rev != fwd {
+        finders.push((memmem::Finder::new(&rev), true));
+    }
+
+    gfa.paths
+        .ids()
+        .collect::<Vec<_>>()
+        .into_par_iter()
+        .flat_map_iter(|path_id| {
+            let path = &gfa.paths[path_id];
+            let mut seq = Vec::new();
+            path_seq_into(gfa, path, 0..usize::MAX, &mut seq);
+            seq.make_ascii_uppercase();
+
+            // The start position of each step, and then the end of the path.
+            let mut starts: Vec<usize> = gfa
+                .get_path_steps(path)
+                .scan(0, |pos, step| {
+                    let start = *pos;
+                    *pos += gfa.get_handle_seg(*step).len();
+                    Some(start)
+                })
+                .collect();
+            starts.push(seq.len());
+
+            let mut out = Vec::new();
+            for (finder, reverse) in &finders {
+                let mut pos = 0;
+                while let Some(i) = finder.find(&seq[pos..]) {
+                    let range = pos + i..pos + i + fwd.len();
+                    let first = starts.partition_point(|&s| s <= range.start) - 1;
+                    let last = starts.partition_point(|&s| s < range.end) - 1;
+                    out.push(PathMatch {
+                        path: path_id,
+                        offset: range.start - starts[first],
+                        steps: first..last + 1,
+                        range,
+                        reverse: *reverse,
+                    });
+                    pos += i + 1;
+                }
+            }
+            out.sort_by_key(|m| m.range.start);
+            out
+        })
+        .collect()
+}
+
+/// Write a comma-separated list of handles.
+fn write_walk(
+    f: &mut impl Write,
+    gfa: &flatgfa::FlatGFA,
+    walk: impl Iterator<Item = Handle>,
+) -> std::io::Result<()> {
+    for (i, handle) in walk.enumerate() {
+        if i > 0 {
+            write!(f, ",")?;
+        }
+        write!(f, "{}", Display(gfa, handle))?;
+    }
+    Ok(())
+}
+
+/// The walks that spell out a query, as a TSV.
+pub struct GraphMatchTable<'a> {
+    pub gfa: &'a flatgfa::FlatGFA<'a>,
+    pub matches: Vec<GraphMatch>,
+}
+
+impl Emit for GraphMatchTable<'_> {
+    fn emit(self, f: &mut impl Write) -> std::io::Result<()> {
+        writeln!(f, "#walk\tstart\tend")?;
+        for m in &self.matches {
+            write_walk(f, self.gfa, m.walk.iter().copied())?;
+            writeln!(f, "\t{}\t{}", m.start, m.end)?;
+        }
+        Ok(())
+    }
+}
+
+/// The occurrences of a query in paths, as a TSV. Each line has the path
+/// coordinates and strand, and the graph position: the steps that the match
+/// touches and the offsets where it starts and ends within them.
+pub struct PathMatchTable<'a> {
+    pub gfa: &'a flatgfa::FlatGFA<'a>,
+    pub matches: Vec<PathMatch>,
+}
+
+impl Emit for PathMatchTable<'_> {
+    fn emit(self, f: &mut impl Write) -> std::io::Result<()> {
+        writeln!(f, "#path\tstart\tend\tstrand\twalk\twalk.start\twalk.end")?;
+        for m in &self.matches {
+            let path = &self.gfa.paths[m.path];
+            let steps = &self.gfa.steps[path.steps][m.steps.clone()];
+            let strand = if m.reverse { '-' } else { '+' };
+            write!(
+                f,
+                "{}\t{}\t{}\t{}\t",
+                self.gfa.get_path_name(path),
+                m.range.start,
+                m.range.end,
+                strand
+            )?;
+            write_walk(f, self.gfa, steps.iter().copied())?;
+
+            // The end offset within the last step.
+            let walk_len: usize = steps
+                .iter()
+                .map(|s| self.gfa.get_handle_seg(*s).len())
+                .sum();
+            let last_len = self.gfa.get_handle_seg(*steps.last().unwrap()).len();
+            let end = last_len - (walk_len - m.offset - m.range.len());
+            writeln!(f, "\t{}\t{}", m.offset, end)?;
+        }
+        Ok(())
+    }
+}
+
+#[test]
+fn test_find_seq() {
+    // Path x spells ACGTTGCA; path y spells ACGT|CCA with segment 3 reversed.
+    let gfa_text = b"S\t1\tACGT\nS\t2\tTGCA\nS\t3\tTGG\n\
+        L\t1\t+\t2\t+\t0M\nL\t1\t+\t3\t-\t0M\n\
+        P\tx\t1+,2+\t*\nP\ty\t1+,3-\t*\n";
+    let store = crate::parse::Parser::for_heap().parse_mem(gfa_text);
+    let gfa = store.as_ref();
+    let seg = |name| gfa.find_seg(name).unwrap();
+
+    // GTCC spans segments 1 and 3 (reversed).
+    let matches = find_in_graph(&gfa, b"gtcc");
+    assert_eq!(matches.len(), 1);
+    assert_eq!(
+        matches[0].walk,
+        vec![
+            seg(1).handle(Orientation::Forward),
+            seg(3).handle(Orientation::Backward)
+        ]
+    );
+    assert_eq!((matches[0].start, matches[0].end), (2, 2));
+
+    // Its reverse complement, GGAC, walks the same segments the other way.
+    let matches = find_in_graph(&gfa, b"GGAC");
+    assert_eq!(matches.len(), 1);
+    assert_eq!(matches[0].walk[0], seg(3).handle(Orientation::Forward));
+
+    // In the paths, TTG occurs forward in x and reverse (as CAA) nowhere.
+    let matches = find_in_paths(&gfa, b"TTG");
+    assert_eq!(matches.len(), 1);
+    assert_eq!(matches[0].range, 3..6);
+    assert_eq!(matches[0].steps, 0..2);
+    assert_eq!(matches[0].offset, 3);
+
+    // GG occurs only as its reverse complement, CC, in y.
+    let matches = find_in_paths(&gfa, b"GG");
+    assert_eq!(matches.len(), 1);
+    assert!(matches[0].reverse);
+    assert_eq!(matches[0].range, 4..6);
+
+    // A cycle of empty segments doesn't trap the search.
+    let gfa_text = b"S\t1\tAC\nS\t2\t\nS\t3\t\nS\t4\tGG\n\
+        L\t1\t+\t2\t+\t0M\nL\t2\t+\t3\t+\t0M\nL\t3\t+\t2\t+\t0M\nL\t1\t+\t4\t+\t0M\n";
+    let store = crate::parse::Parser::for_heap().parse_mem(gfa_text);
+    assert_eq!(find_in_graph(&store.as_ref(), b"ACGG").len(), 1);
+}
@@ -0,0 +1,315 @@
+#![allow(clippy::repr_packed_without_abi)]
+
+use crate::emit::Emit;
+use crate::file;
+use crate::flatgfa::{self, Handle, Orientation};
+use crate::ops::bubble::HandleGraph;
+use crate::print::Display;
+use rayon::prelude::*;
+use std::borrow::Cow;
+use std::io::Write;
+use zerocopy::{FromBytes, Immutable, IntoBytes, KnownLayout};
+
+const MAGIC_NUMBER: u64 = 0xB101_1C3E;
+
+/// The longest k-mer we can pack into a `u64`, at 2 bits per base.
+pub const MAX_K: usize = 31;
+
+/// Give up on a k-mer that crosses segment boundaries after following this many
+/// distinct walks out of a segment, to keep dense tangles from blowing up.
+const MAX_EXTENSIONS: usize = 4096;
+
+/// A single occurrence of a k-mer in the graph: it starts at `offset` in the
+/// (oriented) sequence of `handle`, and it may continue into the segments that
+/// follow.
+#[derive(FromBytes, IntoBytes, Immutable, KnownLayout, Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(packed)]
+pub struct Entry {
+    pub kmer: u64,
+    pub handle: Handle,
+    pub offset: u32,
+}
+
+/// The header for an index section, which is followed by its entries.
+#[derive(FromBytes, IntoBytes, Immutable, KnownLayout, Debug)]
+#[repr(packed)]
+struct Header {
+    magic: u64,
+    k: u64,
+    len: u64,
+}
+
+/// Pack a nucleotide into 2 bits. Other characters (like `N`) have no code.
+fn base_code(c: u8) -> Option<u64> {
+    match c {
+        b'A' | b'a' => Some(0),
+        b'C' | b'c' => Some(1),
+        b'G' | b'g' => Some(2),
+        b'T' | b't' => Some(3),
+        _ => None,
+    }
+}
+
+/// Find every k-mer in a sequence, as `(start, kmer)` pairs.
+///
+/// K-mers that include a base other than A, C, G, or T are skipped.
+fn kmers(seq: &[u8], k: usize) -> impl Iterator<Item = (usize, u64)> + '_ {
+    let mask = (1 << (2 * k)) - 1;
+    let mut kmer = 0;
+    let mut run = 0;
+    seq.iter().enumerate().filter_map(move |(i, &c)| {
+        match base_code(c) {
+            Some(code) => {
+                kmer = ((kmer << 2) | code) & mask;
+                run += 1;
+            }
+            None => run = 0,
+        }
+        (run >= k).then(|| (i + 1 - k, kmer))
+    })
+}
+
+/// Encode a sequence of bases as a k-mer, if it's short enough and it consists
+/// only of A, C, G, and T.
+pub fn encode(seq: &[u8]) -> Option<u64> {
+    if seq.is_empty() || seq.len() > MAX_K {
+        return None;
+    }
+    kmers(seq, seq.len()).next().map(|(_, kmer)| kmer)
+}
+
+/// Decode a k-mer into its bases.
+pub fn decode(kmer: u64, k: usize) -> Vec<u8> {
+    (0..k)
+        .rev()
+        .map(|i| b"ACGT"[((kmer >> (2 * i)) & 3) as usize])
+        .collect()
+}
+
+/// Collect the sequences that can follow a handle, up to `need` bases long, by
+/// walking through its successors. Walks that reach a dead end are shorter. Empty
+/// segments end the walk (so we can't get stuck in a cycle of them).
+fn extensions(
+    gfa: &flatgfa::FlatGFA,
+    graph: &HandleGraph,
+    handle: Handle,
+    need: usize,
+    prefix: &mut Vec<u8>,
+    out: &mut Vec<Vec<u8>>,
+) {
+    for &next in graph.succs(handle) {
+        if out.len() >= MAX_EXTENSIONS {
+            return;
+        }
+        let seq = gfa.get_seq_oriented(next);
+        let len = prefix.len();
+        if seq.len() >= need {
+            seq.slice(0..need).extend_vec(prefix);
+            out.push(prefix.clone());
+        } else if !seq.is_empty() {
+            seq.extend_vec(prefix);
+            if graph.succs(next).is_empty() {
+                out.push(prefix.clone());
+            } else {
+                extensions(gfa, graph, next, need - seq.len(), prefix, out);
+            }
+        }
+        prefix.truncate(len);
+    }
+}
+
+/// Find all the k-mers that start in a given handle.
+fn handle_kmers(
+    gfa: &flatgfa::FlatGFA,
+    graph: &HandleGraph,
+    handle: Handle,
+    k: usize,
+) -> Vec<Entry> {
+    let seq = gfa.get_seq_oriented(handle).to_vec();
+    let entry = |offset: usize, kmer: u64| Entry {
+        kmer,
+        handle,
+        offset: offset as u32,
+    };
+
+    // K-mers entirely within the segment.
+    let mut entries: Vec<Entry> = kmers(&seq, k).map(|(i, kmer)| entry(i, kmer)).collect();
+
+    // K-mers that start in the last k - 1 bases and continue into the successors.
+    let tail_start = seq.len().saturating_sub(k - 1);
+    let tail = &seq[tail_start..];
+    if !tail.is_empty() {
+        let mut exts = Vec::new();
+        let mut prefix = tail.to_vec();
+        extensions(gfa, graph, handle, k - 1, &mut prefix, &mut exts);
+        for ext in &exts {
+            entries.extend(
+                kmers(ext, k)
+                    .take_while(|&(i, _)| i < tail.len())
+                    .map(|(i, kmer)| entry(tail_start + i, kmer)),
+            );
+        }
+    }
+    entries
+}
+
+/// An index from k-mers to their positions in the graph.
+///
+/// The index covers both orientations of every segment, so a k-mer and its
+/// reverse complement are both present wherever either one occurs. The entries
+/// are sorted by k-mer, so lookups are binary searches. Links are assumed to
+/// have no overlap.
+pub struct KmerIndex<'a> {
+    pub k: usize,
+    pub entries: Cow<'a, [Entry]>,
+}
+
+impl KmerIndex<'_> {
+    /// Build an index of all the k-mers in a graph.
+    ///
+    /// This includes k-mers that span segment boundaries, following links. The
+    /// segments are processed in parallel.
+    pub fn build(gfa: &flatgfa::FlatGFA, k: usize) -> KmerIndex<'static> {
+        assert!((1..=MAX_K).contains(&k), "k must be between 1 and {MAX_K}");
+        let graph = HandleGraph::new(gfa);
+        let mut entries: Vec<Entry> = gfa
+            .segs
+            .ids()
+            .collect::<Vec<_>>()
+            .into_par_iter()
+            .flat_map_iter(|id| {
+                let fwd = handle_kmers(gfa, &graph, id.handle(Orientation::Forward), k);
+                let bwd = handle_kmers(gfa, &graph, id.handle(Orientation::Backward), k);
+                fwd.into_iter().chain(bwd)
+            })
+            .collect();
+        entries.par_sort_unstable_by_key(|e| (e.kmer, e.handle, e.offset));
+        entries.dedup();
+        KmerIndex {
+            k,
+            entries: Cow::Owned(entries),
+        }
+    }
+
+    /// Get every occurrence of a k-mer.
+    pub fn lookup(&self, kmer: u64) -> &[Entry] {
+        let start = self.entries.partition_point(|e| e.kmer < kmer);
+        let end = self.entries.partition_point(|e| e.kmer <= kmer);
+        &self.entries[start..end]
+    }
+
+    /// Look up every k-mer in a query sequence. Produces the offset of each
+    /// k-mer in the query along with its occurrences in the graph.
+    pub fn query<'b>(&'b self, seq: &'b [u8]) -> impl Iterator<Item = (usize, &'b [Entry])> {
+        kmers(seq, self.k).map(|(i, kmer)| (i, self.lookup(kmer)))
+    }
+
+    /// Count the distinct k-mers in the index.
+    pub fn distinct(&self) -> usize {
+        self.entries
+            .chunk_by(|a, b| {
+                let (a, b) = (a.kmer, b.kmer);
+                a == b
+            })
+            .count()
+    }
+
+    /// Write the index as a section that can follow a FlatGFA file.
+    pub fn write(&self, f: &mut impl Write) -> std::io::Result<()> {
+        let header = Header {
+            magic: MAGIC_NUMBER,
+            k: self.k as u64,
+            len: self.entries.len() as u64,
+        };
+        f.write_all(header.as_bytes())?;
+        f.write_all(self.entries.as_bytes())
+    }
+
+    /// Get an index backed by the data in a byte buffer, as written by `write`.
+    pub fn view(data: &[u8]) -> Result<KmerIndex<'_>, &'static str> {
+        let (header, rest) = Header::ref_from_prefix(data).map_err(|_| "index too short")?;
+        let (magic, k, len) = (header.magic, header.k, header.len);
+        if magic != MAGIC_NUMBER {
+            return Err("not a k-mer index");
+        }
+        let (entries, _) = <[Entry]>::ref_from_prefix_with_elems(rest, len as usize)
+            .map_err(|_| "index truncated")?;
+        Ok(KmerIndex {
+            k: k as usize,
+            entries: Cow::Borrowed(entries),
+        })
+    }
+
+    /// Find the index saved in a FlatGFA file, after the graph itself.
+    pub fn saved(data: &[u8]) -> Option<KmerIndex<'_>> {
+        Self::view(file::trailer(data)).ok()
+    }
+}
+
+/// The hits for some query sequences, as a TSV.
+pub struct QueryTable<'a> {
+    pub gfa: &'a flatgfa::FlatGFA<'a>,
+    pub index: &'a KmerIndex<'a>,
+    pub queries: &'a [Vec<u8>],
+}
+
+impl Emit for QueryTable<'_> {
+    fn emit(self, f: &mut impl Write) -> std::io::Result<()> {
+        writeln!(f, "#query\tquery.offset\tkmer\thandle\thandle.offset")?;
+        for (idx, seq) in self.queries.iter().enumerate() {
+            for (qoff, hits) in self.index.query(seq) {
+                let kmer = bstr::BStr::new(&seq[qoff..qoff + self.index.k]);
+                for hit in hits {
+                    let offset = hit.offset;
+                    writeln!(
+                        f,
+                        "{}\t{}\t{}\t{}\t{}",
+                        idx,
+                        qoff,
+                        kmer,
+                        Display(self.gfa, hit.handle),
+                        offset
+                    )?;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+#[test]
+fn test_kmer_index() {
+    // Segment 1 is followed by either 2 or 3.
+    let gfa_text = b"S\t1\tACGTA\nS\t2\tCC\nS\t3\tGNG\nL\t1\t+\t2\t+\t0M\nL\t1\t+\t3\t+\t0M\n";
+    let store = crate::parse::Parser::for_heap().parse_mem(gfa_text);
+    let gfa = store.as_ref();
+    let seg = |name| gfa.find_seg(name).unwrap();
+
+    assert_eq!(decode(encode(b"GATTACA").unwrap(), 7), b"GATTACA");
+
+    let index = KmerIndex::build(&gfa, 4);
+    let hits = index.lookup(encode(b"ACGT").unwrap());
+    assert_eq!(hits.len(), 2); // ACGT is its own reverse complement.
+
+    // TACC spans segments 1 and 2.
+    let hits = index.lookup(encode(b"TACC").unwrap());
+    let (handle, offset) = (hits[0].handle, hits[0].offset);
+    assert_eq!(hits.len(), 1);
+    assert_eq!((handle, offset), (seg(1).handle(Orientation::Forward), 3));
+
+    // Its reverse complement starts in the backward handle for segment 2.
+    let hits = index.lookup(encode(b"GGTA").unwrap());
+    let (handle, offset) = (hits[0].handle, hits[0].offset);
+    assert_eq!((handle, offset), (seg(2).handle(Orientation::Backward), 0));
+
+    // GTAG reaches into segment 3, but nothing goes through its N.
+    assert_eq!(index.lookup(encode(b"GTAG").unwrap()).len(), 1);
+    let fwd3 = seg(3).handle(Orientation::Forward);
+    assert!(index.entries.iter().all(|e| e.handle != fwd3));
+
+    let mut buf = Vec::new();
+    index.write(&mut buf).unwrap();
+    let saved = KmerIndex::view(&buf).unwrap();
+    assert_eq!(saved.k, 4);
+    assert_eq!(saved.entries, index.entries);
+}
@@ -0,0 +1,341 @@
+use crate::emit::Emit;
+use crate::flatgfa::{self, Orientation};
+use rayon::prelude::*;
+use std::io::Write;
+use zerocopy::{FromBytes, Immutable, IntoBytes, KnownLayout};
+
+const MAGIC_NUMBER: u64 = 0xB101_1A70;
+
+/// A 2D coordinate for one end of a segment.
+#[derive(FromBytes, IntoBytes, Immutable, KnownLayout, Debug, Clone, Copy, PartialEq)]
+#[repr(C)]
+pub struct Point {
+    pub x: f64,
+    pub y: f64,
+}
+
+/// The header for a binary layout file, which is followed by the points.
+#[derive(FromBytes, IntoBytes, Immutable, KnownLayout, Debug)]
+#[repr(C)]
+struct Header {
+    magic: u64,
+    len: u64,
+}
+
+/// Parameters for the path-guided SGD layout.
+pub struct Config {
+    /// The number of passes over the terms.
+    pub iters: usize,
+
+    /// The number of terms to sample per iteration, per path step.
+    pub terms_per_step: usize,
+
+    /// The final learning rate, relative to the tightest term.
+    pub eps: f64,
+
+    /// The seed for all the random numbers.
+    pub seed: u64,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            iters: 30,
+            terms_per_step: 10,
+            eps: 0.01,
+            seed: 9399220,
+        }
+    }
+}
+
+/// The SplitMix64 pseudorandom number generator.
+///
+/// It's tiny and fast, and it makes it easy to give every chunk of work its own
+/// stream so the layout is deterministic no matter how rayon schedules things.
+struct SplitMix64(u64);
+
+impl SplitMix64 {
+    fn new(seed: u64, stream: u64) -> Self {
+        let mut rng = Self(seed ^ stream.wrapping_mul(0xD1B5_4A32_D192_ED03));
+        rng.next();
+        rng
+    }
+
+    fn next(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+
+    /// A uniform float in [0, 1).
+    fn float(&mut self) -> f64 {
+        (self.next() >> 11) as f64 / (1u64 << 53) as f64
+    }
+
+    /// A uniform integer in [0, n).
+    fn below(&mut self, n: usize) -> usize {
+        (self.float() * n as f64) as usize
+    }
+}
+
+/// The index of a segment end's point: `2 * segment` for the start and
+/// `2 * segment + 1` for the end.
+fn point_idx(seg: usize, end: bool) -> usize {
+    2 * seg + end as usize
+}
+
+/// The path steps, flattened so we can sample them uniformly.
+struct Steps {
+    /// The segment end points (start, end) for each step, in path order.
+    ends: Vec<(u32, u32)>,
+
+    /// The path offset of each step's start.
+    pos: Vec<u64>,
+
+    /// The range of `ends` and `pos` for each path, by path start.
+    paths: Vec<(usize, usize)>,
+}
+
+impl Steps {
+    fn new(gfa: &flatgfa::FlatGFA) -> Self {
+        let mut steps = Steps {
+            ends: Vec::with_capacity(gfa.steps.len()),
+            pos: Vec::with_capacity(gfa.steps.len()),
+            paths: Vec::with_capacity(gfa.paths.len()),
+        };
+        for path in gfa.paths.all() {
+            let start = steps.ends.len();
+            let mut pos = 0;
+            for step in gfa.get_path_steps(path) {
+                let seg = step.segment().index();
+                let (first, last) = (point_idx(seg, false), point_idx(seg, true));
+                steps.ends.push(match step.orient() {
+                    Orientation::Forward => (first as u32, last as u32),
+                    Orientation::Backward => (last as u32, first as u32),
+                });
+                steps.pos.push(pos);
+                pos += gfa.segs[step.segment()].len() as u64;
+            }
+            if steps.ends.len() > start {
+                steps.paths.push((start, steps.ends.len()));
+            }
+        }
+        steps
+    }
+
+    /// Pick a random point on a random step, and return it with its path offset.
+    fn point(&self, gfa: &flatgfa::FlatGFA, step: usize, rng: &mut SplitMix64) -> (usize, f64) {
+        let (start, end) = self.ends[step];
+        if rng.next() & 1 == 0 {
+            (start as usize, self.pos[step] as f64)
+        } else {
+            let len = gfa.segs.all()[start as usize / 2].len();
+            (end as usize, (self.pos[step] + len as u64) as f64)
+        }
+    }
+
+    /// Sample a term: a pair of points on the same path and their distance in
+    /// the path. The second step is a random jump from the first, with jump
+    /// lengths distributed log-uniformly (i.e., roughly Zipfian), so we
+    /// sample nearby steps more often.
+    fn term(&self, gfa: &flatgfa::FlatGFA, rng: &mut SplitMix64) -> Option<(usize, usize, f64)> {
+        let i = rng.below(self.ends.len());
+        let p = self.paths.partition_point(|&(start, _)| start <= i) - 1;
+        let (start, end) = self.paths[p];
+        let n = end - start;
+        if n < 2 {
+            return None;
+        }
+        let jump = (n as f64).powf(rng.float()) as usize;
+        let j = if rng.next() & 1 == 0 {
+            (i + jump.max(1)).min(end - 1)
+        } else {
+            i.saturating_sub(jump.max(1)).max(start)
+        };
+
+        let (a, pos_a) = self.point(gfa, i, rng);
+        let (b, pos_b) = self.point(gfa, j, rng);
+        let dist = (pos_a - pos_b).abs();
+        if a == b || dist == 0.0 {
+            return None;
+        }
+        Some((a, b, dist))
+    }
+}
+
+/// The number of terms each parallel chunk computes at once.
+const CHUNK_SIZE: usize = 1024;
+
+/// The number of chunks whose updates are applied together.
+const BATCH_CHUNKS: usize = 64;
+
+/// Compute a 2D layout with path-guided stochastic gradient descent, as in
+/// `odgi layout`.
+///
+/// There are two points for every segment: its start and its end. The goal is
+/// for the Euclidean distance between any two points to match their distance
+/// along the paths that traverse them. Each update samples a pair of points on
+/// the same path and nudges them toward that distance, with a learning rate
+/// that decays exponentially over the iterations.
+///
+/// Updates happen in batches: we compute each batch's updates in parallel,
+/// from the positions at the start of the batch, and then apply them in order.
+/// Every chunk of work has its own random stream, so the result depends only
+/// on the seed.
+pub fn layout(gfa: &flatgfa::FlatGFA, config: &Config) -> Vec<Point> {
+    let steps = Steps::new(gfa);
+
+    // Start with the segments in order along the x axis, with random y jitter.
+    let mut rng = SplitMix64::new(config.seed, u64::MAX);
+    let mut points = Vec::with_capacity(gfa.segs.len() * 2);
+    let mut x = 0.0;
+    for seg in gfa.segs.all() {
+        points.push(Point {
+            x,
+            y: rng.float() * 100.0,
+        });
+        x += seg.len() as f64;
+        points.push(Point {
+            x,
+            y: rng.float() * 100.0,
+        });
+    }
+    if steps.ends.is_empty() {
+        return points;
+    }
+
+    // The learning rate schedule. The loosest term has weight 1/d_max^2.
+    let d_max = steps
+        .paths
+        .iter()
+        .map(|&(_, end)| {
+            let (start, _) = steps.ends[end - 1];
+            (steps.pos[end - 1] + gfa.segs.all()[start as usize / 2].len() as u64) as f64
+        })
+        .fold(1.0, f64::max);
+    let eta_max = d_max * d_max;
+    let eta_min = config.eps;
+    let lambda = if config.iters > 1 {
+        (eta_max / eta_min).ln() / (config.iters - 1) as f64
+    } else {
+        0.0
+    };
+
+    // Keep batches small enough that few updates in a batch touch the same
+    // point. Otherwise, simultaneous updates would overshoot.
+    let terms = steps.ends.len() * config.terms_per_step;
+    let batch_size = (points.len() / 4).clamp(1, CHUNK_SIZE * BATCH_CHUNKS);
+    let chunk_size = batch_size.min(CHUNK_SIZE);
+    let chunks_per_batch = batch_size / chunk_size;
+    let batches = terms.div_ceil(chunk_size * chunks_per_batch);
+
+    let mut stream = 0;
+    for iter in 0..config.iters {
+        let eta = eta_max * (-lambda * iter as f64).exp();
+        for _ in 0..batches {
+            let updates: Vec<Vec<(usize, usize, f64, f64)>> = (stream..stream + chunks_per_batch)
+                .into_par_iter()
+                .map(|chunk| {
+                    let mut rng = SplitMix64::new(config.seed, chunk as u64);
+                    let points = &points;
+                    (0..chunk_size)
+                        .filter_map(|_| steps.term(gfa, &mut rng))
+                        .map(|(a, b, dist)| {
+                            let mu = (eta / (dist * dist)).min(1.0);
+                            let dx = points[a].x - points[b].x;
+                            let dy = points[a].y - points[b].y;
+                            let mag = (dx * dx + dy * dy).sqrt().max(1e-9);
+                            let r = mu * (mag - dist) / 2.0 / mag;
+                            (a, b, r * dx, r * dy)
+                        })
+                        .collect()
+                })
+                .collect();
+            stream += chunks_per_batch;
+            for (a, b, rx, ry) in updates.into_iter().flatten() {
+                points[a].x -= rx;
+                points[a].y -= ry;
+                points[b].x += rx;
+                points[b].y += ry;
+            }
+        }
+    }
+    points
+}
+
+/// Write a layout in the binary format: a small header and then the points.
+pub fn write_layout(points: &[Point], f: &mut impl Write) -> std::io::Result<()> {
+    let header = Header {
+        magic: MAGIC_NUMBER,
+        len: points.len() as u64,
+    };
+    f.write_all(header.as_bytes())?;
+    f.write_all(points.as_bytes())
+}
+
+/// Read a layout in the binary format.
+pub fn read_layout(data: &[u8]) -> Result<Vec<Point>, &'static str> {
+    let (header, rest) = Header::read_from_prefix(data).map_err(|_| "layout file too short")?;
+    if header.magic != MAGIC_NUMBER {
+        return Err("not a layout file");
+    }
+    let size = std::mem::size_of::<Point>();
+    if rest.len() < header.len as usize * size {
+        return Err("layout file truncated");
+    }
+    // The data may not be aligned for `f64`s, so we copy out each point.
+    Ok(rest
+        .chunks_exact(size)
+        .take(header.len as usize)
+        .map(|chunk| Point::read_from_bytes(chunk).unwrap())
+        .collect())
+}
+
+/// A layout as a TSV, with a line for every segment end.
+pub struct LayoutTable<'a> {
+    pub gfa: &'a flatgfa::FlatGFA<'a>,
+    pub points: Vec<Point>,
+}
+
+impl Emit for LayoutTable<'_> {
+    fn emit(self, f: &mut impl Write) -> std::io::Result<()> {
+        writeln!(f, "#segment\tend\tx\ty")?;
+        for (idx, point) in self.points.iter().enumerate() {
+            let name = self.gfa.segs.all()[idx / 2].name;
+            let end = if idx % 2 == 0 { "start" } else { "end" };
+            writeln!(f, "{}\t{}\t{:.3}\t{:.3}", name, end, point.x, point.y)?;
+        }
+        Ok(())
+    }
+}
+
+#[test]
+fn test_layout() {
+    // A chain with a detour: segment 3 is an insertion in path y.
+    let gfa_text = b"S\t1\tAAAAAAAAAA\nS\t2\tCCCCCCCCCC\nS\t3\tGGGGGGGGGG\nS\t4\tTTTTTTTTTT\n\
+        P\tx\t1+,2+,4+\t*\nP\ty\t1+,2+,3+,4+\t*\n";
+    let store = crate::parse::Parser::for_heap().parse_mem(gfa_text);
+    let gfa = store.as_ref();
+    let config = Config {
+        iters: 30,
+        terms_per_step: 100,
+        ..Config::default()
+    };
+    let points = layout(&gfa, &config);
+    assert_eq!(points.len(), 8);
+    assert_eq!(points, layout(&gfa, &config));
+
+    // Each segment should end up about as long as its sequence. (Not exactly,
+    // because the insertion pulls path x's 2-4 junction apart.)
+    for seg in 0..4 {
+        let (a, b) = (points[2 * seg], points[2 * seg + 1]);
+        let dist = ((a.x - b.x).powi(2) + (a.y - b.y).powi(2)).sqrt();
+        assert!((dist - 10.0).abs() < 3.0, "segment {seg} has length {dist}");
+    }
+
+    let mut buf = Vec::new();
+    write_layout(&points, &mut buf).unwrap();
+    assert_eq!(read_layout(&buf).unwrap(), points);
+}
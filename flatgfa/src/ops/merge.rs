@@ -0,0 +1,170 @@
+use crate::flatgfa::{self, AlignOp, Handle, Segment};
+use crate::ops::norm::{canonical_link, new_store};
+use crate::pool::Id;
+use std::collections::{HashMap, HashSet};
+
+/// How to name the segments in a merged graph.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Naming {
+    /// Keep every segment's name unless it is already taken by an earlier graph,
+    /// in which case it gets a fresh name (larger than any name in the inputs).
+    Collisions,
+
+    /// Shift each graph's names past the largest name in all the earlier graphs,
+    /// so nothing collides.
+    Offset,
+}
+
+/// Combine several graphs into one, keeping all their segments, links, and paths.
+///
+/// The header comes from the first graph. With `dedup`, a segment whose name and
+/// sequence both match a segment from an earlier graph is merged into it (and any
+/// resulting duplicate links are dropped). Paths keep their names, except that a
+/// name already used by an earlier graph gets the graph's index as a suffix
+/// (`name.N`).
+pub fn merge(
+    graphs: &[flatgfa::FlatGFA],
+    naming: Naming,
+    dedup: bool,
+) -> Result<flatgfa::HeapGFAStore, &'static str> {
+    let mut store = match graphs.first() {
+        Some(gfa) => new_store(gfa),
+        None => flatgfa::HeapGFAStore::default(),
+    };
+    let mut names: HashMap<usize, Id<Segment>> = HashMap::new();
+    let mut max_name = 0;
+    let mut links = HashSet::new();
+    let mut path_names = HashSet::new();
+
+    // Fresh names start past every name in every input, so they rarely collide
+    // with a name that a later segment keeps.
+    let mut fresh = graphs
+        .iter()
+        .flat_map(|gfa| gfa.segs.all().iter().map(|seg| seg.name))
+        .max()
+        .unwrap_or(0);
+
+    for (idx, gfa) in graphs.iter().enumerate() {
+        // Add the segments, choosing a name for each. Only the names from
+        // earlier graphs count as taken, but fresh names must also avoid the
+        // names that this graph's segments want.
+        let offset = max_name;
+        let wanted = |name: usize| match naming {
+            Naming::Offset => name + offset,
+            Naming::Collisions => name,
+        };
+        let reserved: HashSet<usize> = gfa.segs.all().iter().map(|s| wanted(s.name)).collect();
+        let mut new_ids = Vec::with_capacity(gfa.segs.len());
+        let mut added = Vec::with_capacity(gfa.segs.len());
+        for seg in gfa.segs.all() {
+            let seq = gfa.get_seq(seg);
+            let name = wanted(seg.name);
+            let name = match names.get(&name) {
+                None => name,
+                Some(&old) => {
+                    let merged = store.as_ref();
+                    if dedup && merged.get_seq(&merged.segs[old]) == seq {
+                        new_ids.push(old);
+                        continue;
+                    }
+                    fresh += 1;
+                    while names.contains_key(&fresh) || reserved.contains(&fresh) {
+                        fresh += 1;
+                    }
+                    fresh
+                }
+            };
+            let id = store.add_seg(name, seq, gfa.get_optional_data(seg));
+            added.push((name, id));
+            max_name = max_name.max(name);
+            new_ids.push(id);
+        }
+        names.extend(added);
+        let tr = |handle: Handle| new_ids[handle.segment().index()].handle(handle.orient());
+
+        for link in gfa.links.all() {
+            let (from, to) = (tr(link.from), tr(link.to));
+            if dedup && !links.insert(canonical_link(from, to)) {
+                continue;
+            }
+            let overlap = gfa.get_alignment(link.overlap).ops.to_vec();
+            store.add_link(from, to, overlap);
+        }
+
+        for path in gfa.paths.all() {
+            let mut name = gfa.get_path_name(path).to_vec();
+            while !path_names.insert(name.clone()) {
+                name.extend_from_slice(format!(".{idx}").as_bytes());
+            }
+            let steps = store.add_steps(gfa.get_path_steps(path).map(|step| tr(*step)));
+            let overlaps: Vec<Vec<AlignOp>> = gfa.overlaps[path.overlaps]
+                .iter()
+                .map(|span| gfa.get_alignment(*span).ops.to_vec())
+                .collect();
+            store.add_path(&name, steps, overlaps.into_iter());
+        }
+    }
+    Ok(store)
+}
+
+#[test]
+fn test_merge() {
+    let a = crate::parse::Parser::for_heap()
+        .parse_mem(b"S\t1\tAA\nS\t2\tCC\nL\t1\t+\t2\t+\t0M\nP\tx\t1+,2+\t*\n");
+    let b = crate::parse::Parser::for_heap()
+        .parse_mem(b"S\t2\tCC\nS\t3\tGG\nL\t2\t+\t3\t+\t0M\nP\ty\t2+,3-\t*\n");
+    let graphs = [a.as_ref(), b.as_ref()];
+    let seg_names = |store: &flatgfa::HeapGFAStore| -> Vec<usize> {
+        store.as_ref().segs.all().iter().map(|s| s.name).collect()
+    };
+
+    let merged = merge(&graphs, Naming::Offset, false).unwrap();
+    assert_eq!(seg_names(&merged), vec![1, 2, 4, 5]);
+    let gfa = merged.as_ref();
+    let y = &gfa.paths[gfa.find_path("y".into()).unwrap()];
+    let steps: Vec<_> = gfa
+        .get_path_steps(y)
+        .map(|s| gfa.get_handle_seg(*s).name)
+        .collect();
+    assert_eq!(steps, vec![4, 5]);
+
+    // Segment 2 is taken, so b's gets a fresh name; 3 is free, so it stays.
+    let merged = merge(&graphs, Naming::Collisions, false).unwrap();
+    assert_eq!(seg_names(&merged), vec![1, 2, 4, 3]);
+
+    let merged = merge(&graphs, Naming::Collisions, true).unwrap();
+    assert_eq!(seg_names(&merged), vec![1, 2, 3]);
+    assert_eq!(merged.as_ref().links.len(), 2);
+
+    // A path name that is already taken gets the graph's index as a suffix.
+    let merged = merge(&[a.as_ref(), a.as_ref()], Naming::Offset, false).unwrap();
+    let gfa = merged.as_ref();
+    assert!(gfa.find_path("x".into()).is_some());
+    assert!(gfa.find_path("x.1".into()).is_some());
+
+    // Renaming b's segment 2 must not take the name of b's segment 3, and
+    // dedup must not merge two segments from the same graph.
+    let b = crate::parse::Parser::for_heap()
+        .parse_mem(b"S\t2\tGG\nS\t3\tGG\nL\t2\t+\t3\t+\t0M\nP\ty\t2+,3+\t*\n");
+    let graphs = [a.as_ref(), b.as_ref()];
+    for dedup in [false, true] {
+        let merged = merge(&graphs, Naming::Collisions, dedup).unwrap();
+        assert_eq!(seg_names(&merged), vec![1, 2, 4, 3]);
+        let gfa = merged.as_ref();
+        let y = &gfa.paths[gfa.find_path("y".into()).unwrap()];
+        let steps: Vec<_> = gfa
+            .get_path_steps(y)
+            .map(|s| gfa.get_handle_seg(*s).name)
+            .collect();
+        assert_eq!(steps, vec![4, 3]);
+        assert_eq!(gfa.links.len(), 2);
+    }
+
+    // With offsets, a segment named 0 lands on a's last name. Its fresh name
+    // must not take the name that the next segment is offset to.
+    let mut c = flatgfa::HeapGFAStore::default();
+    c.add_seg(0, b"TT", b"");
+    c.add_seg(1, b"TT", b"");
+    let merged = merge(&[a.as_ref(), c.as_ref()], Naming::Offset, false).unwrap();
+    assert_eq!(seg_names(&merged), vec![1, 2, 4, 3]);
+}
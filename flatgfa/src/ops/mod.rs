@@ -1,8 +1,29 @@
 pub mod bench;
+pub mod bin;
+pub mod bubble;
 pub mod chop;
+pub mod component;
+pub mod composition;
+pub mod consensus;
+pub mod deconstruct;
 pub mod depth;
+pub mod diff;
+pub mod dot;
 pub mod extract;
+pub mod fasta;
+pub mod find_seq;
 pub mod gaf;
+pub mod kmer;
+pub mod layout;
+pub mod merge;
+pub mod norm;
+pub mod overlap;
 pub mod pangenotype;
+pub mod path_matrix;
 pub mod position;
+pub mod prune;
+pub mod similarity;
+pub mod stats;
+pub mod subset;
+pub mod viz;
 pub mod window_depth;
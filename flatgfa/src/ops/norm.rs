@@ -0,0 +1,368 @@
+use crate::flatgfa::{self, AlignOp, Handle, Orientation, Path, Segment};
+use crate::pool::Id;
+use std::collections::{HashMap, HashSet};
+
+/// Copy a path into a new store, translating each of its steps.
+///
+/// The translation can drop steps by returning `None`. Path overlaps are only
+/// preserved when `keep_overlaps` is set (i.e., when steps are not dropped).
+pub(crate) fn copy_path(
+    store: &mut flatgfa::HeapGFAStore,
+    gfa: &flatgfa::FlatGFA,
+    path: &Path,
+    keep_overlaps: bool,
+    tr: impl Fn(Handle) -> Option<Handle>,
+) {
+    let steps = store.add_steps(gfa.get_path_steps(path).filter_map(|step| tr(*step)));
+    let overlaps: Vec<Vec<AlignOp>> = if keep_overlaps {
+        gfa.overlaps[path.overlaps]
+            .iter()
+            .map(|span| gfa.get_alignment(*span).ops.to_vec())
+            .collect()
+    } else {
+        Vec::new()
+    };
+    store.add_path(gfa.get_path_name(path), steps, overlaps.into_iter());
+}
+
+/// Create a store with the same header as an existing graph.
+pub(crate) fn new_store(gfa: &flatgfa::FlatGFA) -> flatgfa::HeapGFAStore {
+    let mut store = flatgfa::HeapGFAStore::default();
+    if !gfa.header.is_empty() {
+        store.add_header(gfa.header.all());
+    }
+    store
+}
+
+/// Pick a single representative for the two equivalent ways of writing a link.
+///
+/// The link A→B is the same as the link B̄→Ā (the same edge, read backward).
+/// We choose whichever of the two has the smaller pair of handles.
+pub fn canonical_link(from: Handle, to: Handle) -> (Handle, Handle) {
+    let rev = (to.flip(), from.flip());
+    if rev < (from, to) {
+        rev
+    } else {
+        (from, to)
+    }
+}
+
+/// Remove duplicate links.
+///
+/// Links are considered equal if they connect the same handles, including
+/// when one is the reverse of the other (A+→B+ is the same as B-→A-). The
+/// remaining links are written in their canonical orientation and sorted.
+/// When duplicates have different overlaps, the first one wins.
+pub fn dedup_links(gfa: &flatgfa::FlatGFA) -> flatgfa::HeapGFAStore {
+    let mut store = new_store(gfa);
+    for seg in gfa.segs.all() {
+        store.add_seg(seg.name, gfa.get_seq(seg), gfa.get_optional_data(seg));
+    }
+    for path in gfa.paths.all() {
+        copy_path(&mut store, gfa, path, true, Some);
+    }
+
+    let mut links: HashMap<(Handle, Handle), Vec<AlignOp>> = HashMap::new();
+    for link in gfa.links.all() {
+        links
+            .entry(canonical_link(link.from, link.to))
+            .or_insert_with(|| gfa.get_alignment(link.overlap).ops.to_vec());
+    }
+    let mut links: Vec<_> = links.into_iter().collect();
+    links.sort_unstable_by_key(|(ends, _)| *ends);
+    for ((from, to), overlap) in links {
+        store.add_link(from, to, overlap);
+    }
+
+    store
+}
+
+/// Flip the segments that paths only ever traverse backward.
+///
+/// Each such segment gets its sequence reverse-complemented, and every path
+/// step and link that refers to it is flipped to match. Segments that no path
+/// traverses, or that some path traverses forward, are unchanged.
+pub fn flip_backward(gfa: &flatgfa::FlatGFA) -> flatgfa::HeapGFAStore {
+    let mut forward = vec![false; gfa.segs.len()];
+    let mut backward = vec![false; gfa.segs.len()];
+    for step in gfa.steps.all() {
+        match step.orient() {
+            Orientation::Forward => forward[step.segment().index()] = true,
+            Orientation::Backward => backward[step.segment().index()] = true,
+        }
+    }
+    let flip: Vec<bool> = forward
+        .iter()
+        .zip(&backward)
+        .map(|(&fwd, &bwd)| bwd && !fwd)
+        .collect();
+    let tr = |handle: Handle| {
+        if flip[handle.segment().index()] {
+            handle.flip()
+        } else {
+            handle
+        }
+    };
+
+    let mut store = new_store(gfa);
+    for (id, seg) in gfa.segs.items() {
+        if flip[id.index()] {
+            let seq = gfa.get_seq_oriented(id.handle(Orientation::Backward));
+            store.add_seg(seg.name, &seq.to_vec(), gfa.get_optional_data(seg));
+        } else {
+            store.add_seg(seg.name, gfa.get_seq(seg), gfa.get_optional_data(seg));
+        }
+    }
+    for path in gfa.paths.all() {
+        copy_path(&mut store, gfa, path, true, |h| Some(tr(h)));
+    }
+    for link in gfa.links.all() {
+        let overlap = gfa.get_alignment(link.overlap).ops.to_vec();
+        store.add_link(tr(link.from), tr(link.to), overlap);
+    }
+
+    store
+}
+
+/// The unique handle that follows each handle, if there is exactly one.
+///
+/// Adjacency comes from both links and consecutive path steps. A handle maps to
+/// `None` if it has several distinct successors.
+fn unique_successors(gfa: &flatgfa::FlatGFA) -> HashMap<Handle, Option<Handle>> {
+    let mut succ: HashMap<Handle, Option<Handle>> = HashMap::new();
+    let mut add = |from: Handle, to: Handle| {
+        // Every edge also implies its reverse.
+        for (a, b) in [(from, to), (to.flip(), from.flip())] {
+            succ.entry(a)
+                .and_modify(|old| {
+                    if *old != Some(b) {
+                        *old = None;
+                    }
+                })
+                .or_insert(Some(b));
+        }
+    };
+
+    for link in gfa.links.all() {
+        add(link.from, link.to);
+    }
+    for path in gfa.paths.all() {
+        let steps = &gfa.steps[path.steps];
+        for pair in steps.windows(2) {
+            add(pair[0], pair[1]);
+        }
+    }
+    succ
+}
+
+/// Find the simple chains of handles that can be merged into one segment.
+///
+/// Two handles A→B can merge when A's only successor is B, B's only
+/// predecessor is A, they belong to different segments, and no path begins or
+/// ends between them. Every segment appears in exactly one chain (most chains
+/// have a single handle). Chains are ordered by their lowest segment ID.
+fn find_chains(gfa: &flatgfa::FlatGFA) -> Vec<Vec<Handle>> {
+    let succ = unique_successors(gfa);
+
+    // Path ends: a path that starts at B or ends at A can't cross A→B.
+    let mut starts = HashSet::new();
+    let mut ends = HashSet::new();
+    for path in gfa.paths.all() {
+        let steps = &gfa.steps[path.steps];
+        if let (Some(&first), Some(&last)) = (steps.first(), steps.last()) {
+            starts.insert(first);
+            ends.insert(last);
+        }
+    }
+
+    let next = |a: Handle| -> Option<Handle> {
+        let b = succ.get(&a).copied().flatten()?;
+        let mergeable = a.segment() != b.segment()
+            && succ.get(&b.flip()).copied().flatten() == Some(a.flip())
+            && !ends.contains(&a)
+            && !starts.contains(&a.flip())
+            && !starts.contains(&b)
+            && !ends.contains(&b.flip());
+        mergeable.then_some(b)
+    };
+    let prev = |b: Handle| next(b.flip()).map(|a| a.flip());
+
+    let mut assigned = vec![false; gfa.segs.len()];
+    let mut chains = Vec::new();
+    for seg_id in gfa.segs.ids() {
+        if assigned[seg_id.index()] {
+            continue;
+        }
+
+        // Walk backward to the start of the chain, stopping if we go around a cycle.
+        let mut head = seg_id.handle(Orientation::Forward);
+        let mut seen = HashSet::from([seg_id]);
+        while let Some(p) = prev(head) {
+            if !seen.insert(p.segment()) {
+                break;
+            }
+            head = p;
+        }
+
+        // Walk forward to collect the chain.
+        let mut chain = vec![head];
+        assigned[head.segment().index()] = true;
+        let mut cur = head;
+        while let Some(n) = next(cur) {
+            if assigned[n.segment().index()] {
+                break;
+            }
+            assigned[n.segment().index()] = true;
+            chain.push(n);
+            cur = n;
+        }
+        chains.push(chain);
+    }
+    chains
+}
+
+/// Merge simple chains of segments into single segments ("unchop").
+///
+/// See `find_chains` for when segments can merge. Each merged segment takes the
+/// name of its lowest-numbered member. Since this changes the step structure,
+/// path overlaps and the optional data on merged segments are dropped; links
+/// inside a chain disappear and the rest are rewritten to the merged segments.
+pub fn unchop(gfa: &flatgfa::FlatGFA) -> flatgfa::HeapGFAStore {
+    let chains = find_chains(gfa);
+
+    // For every old segment: the chain it belongs to and its position there.
+    let mut place: Vec<(usize, usize)> = vec![(0, 0); gfa.segs.len()];
+    for (chain_idx, chain) in chains.iter().enumerate() {
+        for (pos, handle) in chain.iter().enumerate() {
+            place[handle.segment().index()] = (chain_idx, pos);
+        }
+    }
+
+    // Add the merged segments.
+    let mut store = new_store(gfa);
+    let mut new_ids: Vec<Id<Segment>> = Vec::with_capacity(chains.len());
+    let mut seq = Vec::new();
+    for chain in &chains {
+        let first = gfa.get_handle_seg(chain[0]);
+        let name = chain
+            .iter()
+            .map(|h| gfa.get_handle_seg(*h).name)
+            .min()
+            .unwrap();
+        let id = if chain.len() == 1 {
+            store.add_seg(name, gfa.get_seq(first), gfa.get_optional_data(first))
+        } else {
+            seq.clear();
+            for handle in chain {
+                gfa.get_seq_oriented(*handle).extend_vec(&mut seq);
+            }
+            store.add_seg(name, &seq, b"")
+        };
+        new_ids.push(id);
+    }
+
+    // Rewrite paths: a traversal of a chain becomes one step, which we emit at
+    // whichever end of the chain the path enters.
+    for path in gfa.paths.all() {
+        let keep_overlaps = gfa.get_path_steps(path).all(|step| {
+            let (chain_idx, _) = place[step.segment().index()];
+            chains[chain_idx].len() == 1
+        });
+        copy_path(&mut store, gfa, path, keep_overlaps, |step| {
+            let (chain_idx, pos) = place[step.segment().index()];
+            let last = chains[chain_idx].len() - 1;
+            let id = new_ids[chain_idx];
+            if step == chains[chain_idx][pos] {
+                (pos == 0).then(|| id.handle(Orientation::Forward))
+            } else {
+                (pos == last).then(|| id.handle(Orientation::Backward))
+            }
+        });
+    }
+
+    // Rewrite links, dropping the ones inside chains. Links can only attach
+    // to the outer ends of a chain.
+    let tr_end = |handle: Handle, outgoing: bool| {
+        let (chain_idx, pos) = place[handle.segment().index()];
+        let chain = &chains[chain_idx];
+        let id = new_ids[chain_idx];
+        let forward = handle == chain[pos];
+        let at_end = if forward == outgoing {
+            pos == chain.len() - 1
+        } else {
+            pos == 0
+        };
+        assert!(at_end, "link attached to the middle of a chain");
+        id.handle(if forward {
+            Orientation::Forward
+        } else {
+            Orientation::Backward
+        })
+    };
+    for link in gfa.links.all() {
+        let (from_chain, from_pos) = place[link.from.segment().index()];
+        let (to_chain, _) = place[link.to.segment().index()];
+        if from_chain == to_chain {
+            let chain = &chains[from_chain];
+            let forward_step =
+                chain[from_pos] == link.from && chain.get(from_pos + 1) == Some(&link.to);
+            let backward_step = chain[from_pos] == link.from.flip()
+                && from_pos > 0
+                && chain[from_pos - 1] == link.to.flip();
+            if forward_step || backward_step {
+                continue;
+            }
+        }
+        let overlap = gfa.get_alignment(link.overlap).ops.to_vec();
+        store.add_link(tr_end(link.from, true), tr_end(link.to, false), overlap);
+    }
+
+    store
+}
+
+/// Normalize a graph into a canonical form.
+///
+/// This merges simple chains of segments (`unchop`), then flips segments that
+/// are only traversed backward (`flip_backward`), and finally removes
+/// duplicate links (`dedup_links`). Two graphs that differ only in these
+/// respects should normalize to the same result.
+pub fn norm(gfa: &flatgfa::FlatGFA) -> flatgfa::HeapGFAStore {
+    let unchopped = unchop(gfa);
+    let flipped = flip_backward(&unchopped.as_ref());
+    dedup_links(&flipped.as_ref())
+}
+
+#[test]
+fn test_norm() {
+    // Segments 2 and 3 form a simple chain, which both paths traverse backward.
+    let gfa_text = b"S\t1\tA\nS\t2\tCC\nS\t3\tGT\nS\t4\tT\n\
+        L\t1\t+\t3\t-\t0M\nL\t3\t-\t2\t-\t0M\nL\t2\t+\t3\t+\t0M\n\
+        L\t2\t-\t4\t+\t0M\nL\t4\t-\t2\t+\t0M\n\
+        P\tx\t1+,3-,2-,4+\t*\nP\ty\t3-,2-\t*\n";
+    let store = crate::parse::Parser::for_heap().parse_mem(gfa_text);
+    let normed = norm(&store.as_ref());
+    let gfa = normed.as_ref();
+
+    // Segments 2 and 3 merge, and the merged segment is flipped forward.
+    assert_eq!(gfa.segs.len(), 3);
+    let merged = gfa.find_seg(2).unwrap();
+    assert_eq!(gfa.get_seq(&gfa.segs[merged]), "ACGG");
+
+    // The duplicate link between 2 and 4 is gone.
+    assert_eq!(gfa.links.len(), 2);
+
+    // Paths spell the same sequences as before.
+    for path in gfa.paths.all() {
+        let mut seq = Vec::new();
+        for step in gfa.get_path_steps(path) {
+            assert_eq!(step.orient(), Orientation::Forward);
+            gfa.get_seq_oriented(*step).extend_vec(&mut seq);
+        }
+        let expected: &[u8] = if gfa.get_path_name(path) == "x" {
+            b"AACGGT"
+        } else {
+            b"ACGG"
+        };
+        assert_eq!(seq, expected);
+    }
+}
@@ -0,0 +1,248 @@
+use crate::emit::Emit;
+use crate::flatbed::{BEDEntry, FlatBED, HeapBEDStore};
+use crate::flatgfa::{self, Path};
+use crate::pool::{Id, Store};
+use bstr::BStr;
+use rayon::prelude::*;
+use std::collections::HashMap;
+use std::io::Write;
+
+/// The base-pair start offset of every step in a path, plus the path's total
+/// length as a final element.
+fn step_offsets(gfa: &flatgfa::FlatGFA, path: &Path) -> Vec<usize> {
+    let mut offsets = Vec::with_capacity(path.step_count() + 1);
+    let mut pos = 0;
+    offsets.push(pos);
+    for step in gfa.get_path_steps(path) {
+        pos += gfa.get_handle_seg(*step).len();
+        offsets.push(pos);
+    }
+    offsets
+}
+
+/// For each segment, the indices of the query intervals that touch it.
+type Coverage = Vec<Vec<u32>>;
+
+/// Find the segments covered by each query interval.
+///
+/// The result is indexed by segment ID: each segment gets the (sorted) list of
+/// query interval indices that touch it, even partially. We also return the
+/// path that each query is on, so we can avoid reporting a query's own path.
+fn covered_segs(
+    gfa: &flatgfa::FlatGFA,
+    queries: &FlatBED,
+) -> Result<(Coverage, Vec<Id<Path>>), &'static str> {
+    let mut coverage: Coverage = vec![Vec::new(); gfa.segs.len()];
+    let mut query_paths = Vec::with_capacity(queries.get_num_entries());
+
+    // Cache the step offsets for each path we query, by name.
+    let mut offsets: HashMap<&BStr, (Id<Path>, Vec<usize>)> = HashMap::new();
+
+    for (idx, entry) in queries.entries.all().iter().enumerate() {
+        let name = queries.get_name_of_entry(entry);
+        if !offsets.contains_key(name) {
+            let path_id = gfa.find_path(name).ok_or("path not found")?;
+            offsets.insert(name, (path_id, step_offsets(gfa, &gfa.paths[path_id])));
+        }
+        let (path_id, offs) = &offsets[name];
+        query_paths.push(*path_id);
+
+        // Binary-search for the first step that ends after the interval starts,
+        // and walk forward until steps start after the interval ends.
+        let (start, end) = (entry.start as usize, entry.end as usize);
+        let first = offs[1..].partition_point(|&step_end| step_end <= start);
+        let steps = &gfa.steps[gfa.paths[*path_id].steps];
+        for (step, &step_start) in steps[first..].iter().zip(&offs[first..]) {
+            if step_start >= end {
+                break;
+            }
+            let segs = &mut coverage[step.segment().index()];
+            if segs.last() != Some(&(idx as u32)) {
+                segs.push(idx as u32);
+            }
+        }
+    }
+
+    Ok((coverage, query_paths))
+}
+
+/// A single run of steps along some path that overlaps a query interval.
+struct Hit {
+    query: u32,
+    path: Id<Path>,
+    start: usize,
+    end: usize,
+}
+
+/// Find the runs of steps in one path that pass through the query segments.
+///
+/// A "run" is a maximal sequence of consecutive steps that all touch segments
+/// covered by the same query interval.
+fn path_hits(
+    gfa: &flatgfa::FlatGFA,
+    path_id: Id<Path>,
+    coverage: &[Vec<u32>],
+    query_paths: &[Id<Path>],
+) -> Vec<Hit> {
+    let mut hits = Vec::new();
+
+    // The open run for each query: the step index where it last matched, and
+    // its start and end positions.
+    let mut open: HashMap<u32, (usize, usize, usize)> = HashMap::new();
+
+    let path = &gfa.paths[path_id];
+    let mut pos = 0;
+    for (idx, step) in gfa.get_path_steps(path).enumerate() {
+        let end = pos + gfa.get_handle_seg(*step).len();
+        for &query in &coverage[step.segment().index()] {
+            if query_paths[query as usize] == path_id {
+                continue;
+            }
+            match open.get_mut(&query) {
+                Some(run) if run.0 + 1 == idx => {
+                    // Extend the current run.
+                    run.0 = idx;
+                    run.2 = end;
+                }
+                Some(run) => {
+                    // Close the old run and start a new one.
+                    hits.push(Hit {
+                        query,
+                        path: path_id,
+                        start: run.1,
+                        end: run.2,
+                    });
+                    *run = (idx, pos, end);
+                }
+                None => {
+                    open.insert(query, (idx, pos, end));
+                }
+            }
+        }
+        pos = end;
+    }
+
+    // Close any runs that reach the end of the path.
+    hits.extend(open.into_iter().map(|(query, (_, start, end))| Hit {
+        query,
+        path: path_id,
+        start,
+        end,
+    }));
+    hits
+}
+
+/// Find the paths that overlap a set of intervals on other paths.
+///
+/// Each query interval is named by its path. We find every segment that the
+/// interval touches and then report, for every *other* path, the base-pair
+/// intervals where it passes through those segments. Coordinates are at
+/// segment granularity: a hit covers entire segments.
+///
+/// The result is a BED store of hits (named by the overlapping path) along
+/// with the index of the query interval that produced each hit. Hits are
+/// ordered by query, then by path, then by position.
+pub fn overlap(
+    gfa: &flatgfa::FlatGFA,
+    queries: &FlatBED,
+) -> Result<(HeapBEDStore, Vec<usize>), &'static str> {
+    let (coverage, query_paths) = covered_segs(gfa, queries)?;
+
+    // Walk each path in parallel.
+    let mut hits: Vec<Hit> = gfa
+        .paths
+        .ids()
+        .collect::<Vec<_>>()
+        .into_par_iter()
+        .flat_map_iter(|path_id| path_hits(gfa, path_id, &coverage, &query_paths))
+        .collect();
+    hits.sort_by_key(|hit| (hit.query, hit.path.index(), hit.start));
+
+    // Collect the results into a BED store.
+    let mut store = HeapBEDStore::default();
+    let mut names = HashMap::new();
+    let mut query_idxs = Vec::with_capacity(hits.len());
+    for hit in hits {
+        let name = *names.entry(hit.path).or_insert_with(|| {
+            let path = &gfa.paths[hit.path];
+            store.name_data.add_slice(gfa.get_path_name(path))
+        });
+        store.entries.add(BEDEntry {
+            name,
+            start: hit.start as u64,
+            end: hit.end as u64,
+        });
+        query_idxs.push(hit.query as usize);
+    }
+    Ok((store, query_idxs))
+}
+
+/// A printable table of path overlaps.
+///
+/// Each line has a query interval followed by an overlapping path interval.
+pub struct OverlapTable<'a> {
+    pub queries: FlatBED<'a>,
+    pub hits: FlatBED<'a>,
+    pub query_idxs: Vec<usize>,
+}
+
+impl Emit for OverlapTable<'_> {
+    fn emit(self, f: &mut impl Write) -> std::io::Result<()> {
+        writeln!(
+            f,
+            "#path\tstart\tend\tpath.touched\tstart.touched\tend.touched"
+        )?;
+        for (hit, &query_idx) in self.hits.entries.all().iter().zip(&self.query_idxs) {
+            let query = &self.queries.entries.all()[query_idx];
+            let (q_start, q_end) = (query.start, query.end);
+            let (h_start, h_end) = (hit.start, hit.end);
+            writeln!(
+                f,
+                "{}\t{}\t{}\t{}\t{}\t{}",
+                self.queries.get_name_of_entry(query),
+                q_start,
+                q_end,
+                self.hits.get_name_of_entry(hit),
+                h_start,
+                h_end,
+            )?;
+        }
+        Ok(())
+    }
+}
+
+#[test]
+fn test_overlap() {
+    // Path x is AAAA|CC|GGG. Path y takes segment 3 backward, and z takes 2 then 3.
+    let gfa_text = b"S\t1\tAAAA\nS\t2\tCC\nS\t3\tGGG\n\
+        P\tx\t1+,2+,3+\t*\nP\ty\t3-,1+\t*\nP\tz\t2+,3-\t*\n";
+    let store = crate::parse::Parser::for_heap().parse_mem(gfa_text);
+    let gfa = store.as_ref();
+
+    // x:5-7 covers part of segment 2 and part of segment 3.
+    let queries = crate::flatbed::BEDParser::for_heap().parse_mem(b"x\t5\t7\n");
+    let (hits, query_idxs) = overlap(&gfa, &queries.as_ref()).unwrap();
+    let hits = hits.as_ref();
+    let found: Vec<_> = hits
+        .entries
+        .all()
+        .iter()
+        .map(|e| (hits.get_name_of_entry(e).to_string(), e.start, e.end))
+        .collect();
+
+    // Hits cover whole segments: y only touches segment 3, at its start, while
+    // z's run covers both segments.
+    assert_eq!(
+        found,
+        vec![("y".to_string(), 0, 3), ("z".to_string(), 0, 5)]
+    );
+    assert_eq!(query_idxs, vec![0, 0]);
+
+    // x:0-4 covers only segment 1, which z never visits.
+    let queries = crate::flatbed::BEDParser::for_heap().parse_mem(b"x\t0\t4\n");
+    let (hits, _) = overlap(&gfa, &queries.as_ref()).unwrap();
+    let hits = hits.as_ref();
+    assert_eq!(hits.get_num_entries(), 1);
+    let entry = hits.entries.all()[0];
+    assert_eq!((entry.start, entry.end), (3, 7));
+}
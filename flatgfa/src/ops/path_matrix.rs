@@ -0,0 +1,237 @@
+#![allow(clippy::repr_packed_without_abi)]
+
+use crate::flatgfa::{self, Path};
+use crate::ops::position::path_len;
+use crate::pool::Id;
+use rayon::prelude::*;
+use std::io::Write;
+use zerocopy::{FromBytes, Immutable, IntoBytes, KnownLayout};
+
+const MAGIC_NUMBER: u64 = 0x9A7B_3A7C;
+
+/// A path-by-segment coverage matrix.
+///
+/// Each row is a path and each column is a segment (in segment ID order). An
+/// entry is the number of times that the path traverses the segment. Since
+/// most paths touch only a small fraction of a big graph, we store the matrix
+/// in compressed sparse row (CSR) form, as in SciPy's `csr_matrix`.
+pub struct PathMatrix {
+    /// The paths for each row.
+    pub paths: Vec<Id<Path>>,
+
+    /// The number of columns, i.e., segments in the graph.
+    pub cols: usize,
+
+    /// The row `i` entries are at `indptr[i]..indptr[i + 1]` in `indices` and `data`.
+    pub indptr: Vec<u64>,
+
+    /// The column (segment ID) of each nonzero entry.
+    pub indices: Vec<u32>,
+
+    /// The count for each nonzero entry.
+    pub data: Vec<u32>,
+}
+
+/// The header for the binary matrix format.
+///
+/// The header is followed by three arrays: `indptr` (`u64`s), `indices`
+/// (`u32`s), and `data` (`u32`s), just like the fields in `PathMatrix`.
+#[derive(FromBytes, IntoBytes, Immutable, KnownLayout)]
+#[repr(packed)]
+struct MatrixHeader {
+    magic: u64,
+    rows: u64,
+    cols: u64,
+    nnz: u64,
+}
+
+/// Count the traversals of each segment in one path, as sorted (segment, count) pairs.
+fn path_counts(gfa: &flatgfa::FlatGFA, path: &Path) -> Vec<(u32, u32)> {
+    let mut segs: Vec<u32> = gfa
+        .get_path_steps(path)
+        .map(|step| step.segment().into())
+        .collect();
+    segs.sort_unstable();
+
+    let mut counts: Vec<(u32, u32)> = Vec::new();
+    for seg in segs {
+        match counts.last_mut() {
+            Some((last, count)) if *last == seg => *count += 1,
+            _ => counts.push((seg, 1)),
+        }
+    }
+    counts
+}
+
+/// Compute the coverage matrix for some paths in the graph.
+///
+/// Paths are processed in parallel.
+pub fn path_matrix(gfa: &flatgfa::FlatGFA, paths: impl Iterator<Item = Id<Path>>) -> PathMatrix {
+    let paths: Vec<_> = paths.collect();
+    let rows: Vec<Vec<(u32, u32)>> = paths
+        .par_iter()
+        .map(|&id| path_counts(gfa, &gfa.paths[id]))
+        .collect();
+
+    let nnz = rows.iter().map(|row| row.len()).sum();
+    let mut indptr = Vec::with_capacity(rows.len() + 1);
+    let mut indices = Vec::with_capacity(nnz);
+    let mut data = Vec::with_capacity(nnz);
+    indptr.push(0);
+    for row in rows {
+        for (seg, count) in row {
+            indices.push(seg);
+            data.push(count);
+        }
+        indptr.push(indices.len() as u64);
+    }
+
+    PathMatrix {
+        paths,
+        cols: gfa.segs.len(),
+        indptr,
+        indices,
+        data,
+    }
+}
+
+impl PathMatrix {
+    /// The number of rows (paths).
+    pub fn rows(&self) -> usize {
+        self.indptr.len() - 1
+    }
+
+    /// The number of nonzero entries.
+    pub fn nnz(&self) -> usize {
+        self.data.len()
+    }
+
+    /// Get the (column, count) pairs for the nonzero entries in a row.
+    pub fn row(&self, row: usize) -> impl Iterator<Item = (u32, u32)> + '_ {
+        let range = (self.indptr[row] as usize)..(self.indptr[row + 1] as usize);
+        self.indices[range.clone()]
+            .iter()
+            .copied()
+            .zip(self.data[range].iter().copied())
+    }
+
+    /// Expand a row into a dense vector of counts.
+    pub fn dense_row(&self, row: usize) -> Vec<u32> {
+        let mut dense = vec![0; self.cols];
+        for (col, count) in self.row(row) {
+            dense[col as usize] = count;
+        }
+        dense
+    }
+
+    /// Write the matrix as a dense, odgi-style TSV table.
+    ///
+    /// Like `odgi paths -H`, each row has the path name, its length in base
+    /// pairs, and its step count, followed by one column per segment.
+    pub fn write_tsv(&self, gfa: &flatgfa::FlatGFA, f: &mut impl Write) -> std::io::Result<()> {
+        write!(f, "path.name\tpath.length\tpath.step.count")?;
+        for seg in gfa.segs.all() {
+            let name = seg.name;
+            write!(f, "\tnode.{name}")?;
+        }
+        writeln!(f)?;
+
+        for (row, &id) in self.paths.iter().enumerate() {
+            let path = &gfa.paths[id];
+            let len = path_len(gfa, path);
+            write!(
+                f,
+                "{}\t{}\t{}",
+                gfa.get_path_name(path),
+                len,
+                path.step_count()
+            )?;
+            for count in self.dense_row(row) {
+                write!(f, "\t{count}")?;
+            }
+            writeln!(f)?;
+        }
+        Ok(())
+    }
+
+    /// Write the matrix in the MatrixMarket coordinate format.
+    ///
+    /// Rows and columns are 1-based, as the format requires. Row `i` is the
+    /// `i`th path in the matrix and column `j` is the `j`th segment.
+    pub fn write_mtx(&self, f: &mut impl Write) -> std::io::Result<()> {
+        writeln!(f, "%%MatrixMarket matrix coordinate integer general")?;
+        writeln!(f, "{} {} {}", self.rows(), self.cols, self.nnz())?;
+        for row in 0..self.rows() {
+            for (col, count) in self.row(row) {
+                writeln!(f, "{} {} {}", row + 1, col + 1, count)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Write the matrix in a compact binary CSR format.
+    ///
+    /// The format is a `MatrixHeader` followed by the raw `indptr`, `indices`,
+    /// and `data` arrays, in native byte order (like FlatGFA files).
+    pub fn write_binary(&self, f: &mut impl Write) -> std::io::Result<()> {
+        let header = MatrixHeader {
+            magic: MAGIC_NUMBER,
+            rows: self.rows() as u64,
+            cols: self.cols as u64,
+            nnz: self.nnz() as u64,
+        };
+        f.write_all(header.as_bytes())?;
+        f.write_all(self.indptr.as_bytes())?;
+        f.write_all(self.indices.as_bytes())?;
+        f.write_all(self.data.as_bytes())?;
+        Ok(())
+    }
+
+    /// Read a matrix from the binary format produced by `write_binary`.
+    ///
+    /// The result has no path IDs, since the binary format doesn't record them.
+    pub fn read_binary(buf: &[u8]) -> Self {
+        let (header, rest) = MatrixHeader::read_from_prefix(buf).unwrap();
+        let magic = header.magic;
+        assert_eq!(magic, MAGIC_NUMBER);
+        let (rows, nnz) = (header.rows as usize, header.nnz as usize);
+
+        let (indptr, rest) = rest.split_at((rows + 1) * size_of::<u64>());
+        let (indices, data) = rest.split_at(nnz * size_of::<u32>());
+        Self {
+            paths: Vec::new(),
+            cols: header.cols as usize,
+            indptr: indptr
+                .chunks_exact(size_of::<u64>())
+                .map(|b| u64::from_ne_bytes(b.try_into().unwrap()))
+                .collect(),
+            indices: indices
+                .chunks_exact(size_of::<u32>())
+                .map(|b| u32::from_ne_bytes(b.try_into().unwrap()))
+                .collect(),
+            data: data[..nnz * size_of::<u32>()]
+                .chunks_exact(size_of::<u32>())
+                .map(|b| u32::from_ne_bytes(b.try_into().unwrap()))
+                .collect(),
+        }
+    }
+}
+
+#[test]
+fn test_binary_round_trip() {
+    let gfa_text = b"S\t1\tA\nS\t2\tCC\nS\t3\tGGG\nP\tx\t1+,2+,1-\t*\nP\ty\t3+\t*\n";
+    let store = crate::parse::Parser::for_heap().parse_mem(gfa_text);
+    let gfa = store.as_ref();
+    let matrix = path_matrix(&gfa, gfa.paths.ids());
+    assert_eq!(matrix.dense_row(0), vec![2, 1, 0]);
+    assert_eq!(matrix.dense_row(1), vec![0, 0, 1]);
+
+    let mut buf = Vec::new();
+    matrix.write_binary(&mut buf).unwrap();
+    let read = PathMatrix::read_binary(&buf);
+    assert_eq!(read.rows(), 2);
+    assert_eq!(read.cols, 3);
+    assert_eq!(read.indptr, matrix.indptr);
+    assert_eq!(read.indices, matrix.indices);
+    assert_eq!(read.data, matrix.data);
+}
@@ -1,21 +1,110 @@
 use crate::flatgfa;
+use crate::pool::Id;
+use bstr::{BStr, BString};
+use std::ops::Range;
+use std::str::FromStr;
 
 pub fn position(
     gfa: &flatgfa::FlatGFA,
     path: &flatgfa::Path,
     offset: usize,
 ) -> Option<(flatgfa::Handle, usize)> {
+    let (step, start) = step_at(gfa, path, offset)?;
+    Some((gfa.steps[step], offset - start))
+}
+
+/// Find the step of a path that covers a base-pair offset. Produces the step's
+/// id and the offset where the step begins.
+pub fn step_at(
+    gfa: &flatgfa::FlatGFA,
+    path: &flatgfa::Path,
+    offset: usize,
+) -> Option<(Id<flatgfa::Handle>, usize)> {
     // Traverse the path until we reach the position.
     let mut cur_pos = 0;
-    for step in &gfa.steps[path.steps] {
-        let seg = gfa.get_handle_seg(*step);
+    for step in path.steps {
+        let seg = gfa.get_handle_seg(gfa.steps[step]);
         let end_pos = cur_pos + seg.len();
         if offset < end_pos {
             // Found it!
-            return Some((*step, offset - cur_pos));
+            return Some((step, cur_pos));
         }
         cur_pos = end_pos;
     }
 
     None
 }
+
+/// A path name with an optional base-pair range, written `name:start-end`.
+///
+/// The range is half-open (BED-style). If the text after the last colon does
+/// not look like a range, the whole string is the path name; this way, path
+/// names that themselves contain colons still work.
+#[derive(Debug, PartialEq, Clone)]
+pub struct PathRange {
+    pub name: BString,
+    pub range: Option<Range<usize>>,
+}
+
+impl FromStr for PathRange {
+    type Err = &'static str;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if let Some((name, range)) = s.rsplit_once(':') {
+            if let Some((start, end)) = range.split_once('-') {
+                if let (Ok(start), Ok(end)) = (start.parse(), end.parse()) {
+                    if start > end {
+                        return Err("range start must not exceed its end");
+                    }
+                    return Ok(Self {
+                        name: name.into(),
+                        range: Some(start..end),
+                    });
+                }
+            }
+        }
+        Ok(Self {
+            name: s.into(),
+            range: None,
+        })
+    }
+}
+
+impl PathRange {
+    /// Get the path name as a string.
+    pub fn name(&self) -> &BStr {
+        self.name.as_ref()
+    }
+
+    /// Get the base-pair range, using the whole path if none was given.
+    pub fn range_or(&self, len: usize) -> Range<usize> {
+        match &self.range {
+            Some(range) => range.start.min(len)..range.end.min(len),
+            None => 0..len,
+        }
+    }
+}
+
+/// Get the total length (in base pairs) of a path.
+pub fn path_len(gfa: &flatgfa::FlatGFA, path: &flatgfa::Path) -> usize {
+    gfa.get_path_steps(path)
+        .map(|step| gfa.get_handle_seg(*step).len())
+        .sum()
+}
+
+#[test]
+fn test_parse_path_range() {
+    let r: PathRange = "HG002#1#chr20:10-250".parse().unwrap();
+    assert_eq!(r.name, "HG002#1#chr20");
+    assert_eq!(r.range, Some(10..250));
+
+    let r: PathRange = "x".parse().unwrap();
+    assert_eq!(r.name, "x");
+    assert_eq!(r.range, None);
+
+    let r: PathRange = "odd:name".parse().unwrap();
+    assert_eq!(r.name, "odd:name");
+    assert_eq!(r.range, None);
+
+    assert!("x:9-3".parse::<PathRange>().is_err());
+}
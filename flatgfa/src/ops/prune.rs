@@ -0,0 +1,264 @@
+use crate::emit::Emit;
+use crate::flatgfa::{self, Handle, Orientation, Path, Segment};
+use crate::ops::depth::seg_depth;
+use crate::ops::norm::{canonical_link, copy_path, new_store};
+use crate::pool::{Id, Store};
+use crate::print::Display;
+use std::collections::HashSet;
+use std::io::Write;
+
+/// What to remove from a graph.
+pub struct Criteria {
+    /// Remove segments with depth (the number of path traversals) below this.
+    pub min_depth: usize,
+
+    /// Remove tips (segments with nothing attached to one of their ends) that
+    /// are shorter than this.
+    pub max_tip_len: usize,
+
+    /// Remove links that no path follows.
+    pub unused_links: bool,
+}
+
+/// Why a segment was removed.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum SegReason {
+    Depth,
+    Tip,
+}
+
+/// Why a link was removed.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum LinkReason {
+    /// No path follows the link.
+    Unused,
+    /// The link attaches to a removed segment.
+    Segment,
+}
+
+/// Everything that `prune` removed from a graph.
+pub struct Report<'a> {
+    pub gfa: &'a flatgfa::FlatGFA<'a>,
+    pub segs: Vec<(Id<Segment>, SegReason)>,
+    pub links: Vec<(Handle, Handle, LinkReason)>,
+
+    /// Paths that crossed removed segments, with the number of pieces that
+    /// remain (zero if the path is gone entirely).
+    pub paths: Vec<(Id<Path>, usize)>,
+}
+
+/// Find the handles with at least one successor, via links or path steps.
+fn has_successor(gfa: &flatgfa::FlatGFA) -> HashSet<Handle> {
+    let mut out = HashSet::new();
+    let mut add = |from: Handle, to: Handle| {
+        out.insert(from);
+        out.insert(to.flip());
+    };
+    for link in gfa.links.all() {
+        add(link.from, link.to);
+    }
+    for path in gfa.paths.all() {
+        for pair in gfa.steps[path.steps].windows(2) {
+            add(pair[0], pair[1]);
+        }
+    }
+    out
+}
+
+/// Remove low-depth segments, short tips, and unused links from a graph.
+///
+/// Depth comes from `depth::seg_depth`. Tips are found in a single pass, so
+/// removing a tip can leave a new tip behind. Links that attach to a removed
+/// segment are removed too. Paths that traverse removed segments are split
+/// into the pieces that remain, which are named `name:start-end` with offsets
+/// in the original path (as in `extract`). The new graph is compacted: the
+/// remaining segments are renumbered from 1, so there are no gaps where the
+/// removed segments used to be.
+pub fn prune<'a>(
+    gfa: &'a flatgfa::FlatGFA<'a>,
+    criteria: &Criteria,
+) -> (flatgfa::HeapGFAStore, Report<'a>) {
+    let mut report = Report {
+        gfa,
+        segs: Vec::new(),
+        links: Vec::new(),
+        paths: Vec::new(),
+    };
+
+    // Decide which segments to remove.
+    let depth = seg_depth(gfa);
+    let succ = has_successor(gfa);
+    let mut removed = vec![false; gfa.segs.len()];
+    for (id, seg) in gfa.segs.items() {
+        let reason = if depth[id.index()] < criteria.min_depth {
+            Some(SegReason::Depth)
+        } else if seg.len() < criteria.max_tip_len
+            && !(succ.contains(&id.handle(Orientation::Forward))
+                && succ.contains(&id.handle(Orientation::Backward)))
+        {
+            Some(SegReason::Tip)
+        } else {
+            None
+        };
+        if let Some(reason) = reason {
+            removed[id.index()] = true;
+            report.segs.push((id, reason));
+        }
+    }
+
+    let mut store = new_store(gfa);
+    let mut new_ids: Vec<Option<Id<Segment>>> = vec![None; gfa.segs.len()];
+    for (id, seg) in gfa.segs.items() {
+        if !removed[id.index()] {
+            let name = store.segs.len() + 1;
+            let new_id = store.add_seg(name, gfa.get_seq(seg), gfa.get_optional_data(seg));
+            new_ids[id.index()] = Some(new_id);
+        }
+    }
+    let tr =
+        |handle: Handle| new_ids[handle.segment().index()].map(|id| id.handle(handle.orient()));
+
+    // Copy intact paths, and split the others around the removed segments.
+    let mut used = HashSet::new();
+    for (path_id, path) in gfa.paths.items() {
+        let steps = &gfa.steps[path.steps];
+        for pair in steps.windows(2) {
+            used.insert(canonical_link(pair[0], pair[1]));
+        }
+        if steps.iter().all(|s| !removed[s.segment().index()]) {
+            copy_path(&mut store, gfa, path, true, tr);
+            continue;
+        }
+
+        let name = gfa.get_path_name(path);
+        let mut pieces = 0;
+        let mut pos = 0;
+        let mut start = 0;
+        let mut piece = Vec::new();
+        let mut add_piece =
+            |store: &mut flatgfa::HeapGFAStore, piece: &mut Vec<Handle>, start, end| {
+                if !piece.is_empty() {
+                    let span = store.add_steps(piece.drain(..));
+                    let piece_name = format!("{name}:{start}-{end}");
+                    store.add_path(piece_name.as_bytes(), span, std::iter::empty());
+                    pieces += 1;
+                }
+            };
+        for step in steps {
+            match tr(*step) {
+                Some(handle) => {
+                    if piece.is_empty() {
+                        start = pos;
+                    }
+                    piece.push(handle);
+                }
+                None => add_piece(&mut store, &mut piece, start, pos),
+            }
+            pos += gfa.get_handle_seg(*step).len();
+        }
+        add_piece(&mut store, &mut piece, start, pos);
+        report.paths.push((path_id, pieces));
+    }
+
+    // Copy the links that survive.
+    for link in gfa.links.all() {
+        let reason = match (tr(link.from), tr(link.to)) {
+            (Some(from), Some(to)) => {
+                if !criteria.unused_links || used.contains(&canonical_link(link.from, link.to)) {
+                    let overlap = gfa.get_alignment(link.overlap).ops.to_vec();
+                    store.add_link(from, to, overlap);
+                    continue;
+                }
+                LinkReason::Unused
+            }
+            _ => LinkReason::Segment,
+        };
+        report.links.push((link.from, link.to, reason));
+    }
+
+    (store, report)
+}
+
+impl Emit for Report<'_> {
+    /// Write a TSV with a line for every removed segment and link, and every
+    /// path that was split or removed.
+    fn emit(self, f: &mut impl Write) -> std::io::Result<()> {
+        writeln!(f, "#type\tname\treason")?;
+        for (id, reason) in &self.segs {
+            let reason = match reason {
+                SegReason::Depth => "depth",
+                SegReason::Tip => "tip",
+            };
+            let name = self.gfa.segs[*id].name;
+            writeln!(f, "S\t{name}\t{reason}")?;
+        }
+        for (from, to, reason) in &self.links {
+            let reason = match reason {
+                LinkReason::Unused => "unused",
+                LinkReason::Segment => "segment",
+            };
+            writeln!(
+                f,
+                "L\t{},{}\t{}",
+                Display(self.gfa, *from),
+                Display(self.gfa, *to),
+                reason
+            )?;
+        }
+        for (id, pieces) in &self.paths {
+            let name = self.gfa.get_path_name(&self.gfa.paths[*id]);
+            if *pieces == 0 {
+                writeln!(f, "P\t{name}\tremoved")?;
+            } else {
+                writeln!(f, "P\t{name}\tsplit:{pieces}")?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[test]
+fn test_prune() {
+    // Segment 3 is a short tip that no path uses. Path y is the only one
+    // through segment 2.
+    let gfa_text = b"S\t1\tAAAA\nS\t2\tC\nS\t3\tG\nS\t4\tTTTT\n\
+        L\t1\t+\t2\t+\t0M\nL\t2\t+\t4\t+\t0M\nL\t1\t+\t3\t+\t0M\nL\t1\t+\t4\t+\t0M\n\
+        P\tx\t1+,4+\t*\nP\ty\t1+,2+,4+\t*\n";
+    let store = crate::parse::Parser::for_heap().parse_mem(gfa_text);
+    let gfa = store.as_ref();
+
+    let criteria = Criteria {
+        min_depth: 0,
+        max_tip_len: 2,
+        unused_links: true,
+    };
+    let (pruned, report) = prune(&gfa, &criteria);
+    assert_eq!(report.segs.len(), 1);
+    assert_eq!(report.segs[0].1, SegReason::Tip);
+    assert_eq!(pruned.as_ref().segs.len(), 3);
+    assert_eq!(pruned.as_ref().links.len(), 3);
+
+    let criteria = Criteria {
+        min_depth: 2,
+        max_tip_len: 0,
+        unused_links: false,
+    };
+    let (pruned, report) = prune(&gfa, &criteria);
+    let pruned = pruned.as_ref();
+    assert_eq!(report.segs.len(), 2);
+    assert_eq!(report.paths, vec![(gfa.find_path("y".into()).unwrap(), 2)]);
+    assert_eq!(pruned.segs.len(), 2);
+    assert_eq!(pruned.links.len(), 1);
+
+    // Segments 1 and 4 remain, renumbered as 1 and 2.
+    let seg_names: Vec<_> = pruned.segs.all().iter().map(|s| s.name).collect();
+    assert_eq!(seg_names, vec![1, 2]);
+    assert_eq!(pruned.get_seq(&pruned.segs.all()[1]), b"TTTT");
+    let names: Vec<_> = pruned
+        .paths
+        .all()
+        .iter()
+        .map(|p| pruned.get_path_name(p).to_string())
+        .collect();
+    assert_eq!(names, vec!["x", "y:0-4", "y:5-9"]);
+}
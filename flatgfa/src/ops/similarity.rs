@@ -0,0 +1,215 @@
+use crate::emit::Emit;
+use crate::flatgfa::{self, Path};
+use crate::ops::depth::format_float;
+use crate::pool::Id;
+use bit_vec::BitVec;
+use bstr::BString;
+use rayon::prelude::*;
+use std::io::Write;
+
+/// Pairwise similarities between sets of paths, based on the segments they share.
+///
+/// Each set is usually a single path, but it can also be a group of paths (e.g.,
+/// all the contigs in a haplotype). Similarities are weighted by sequence length:
+/// a set's size is the total length of the distinct segments its paths traverse.
+pub struct Similarity {
+    /// The size (in base pairs) of each set.
+    pub sizes: Vec<usize>,
+
+    /// The size of the intersection between each pair of sets `i < j`, in
+    /// condensed order (as in SciPy's `pdist`).
+    pub intersections: Vec<usize>,
+}
+
+/// Find the distinct segments that some paths traverse, in ID order.
+///
+/// Like `depth::seg_depth_with_uniq`, this uses a bit vector to track which
+/// segments we have seen.
+fn seg_set(gfa: &flatgfa::FlatGFA, paths: &[Id<Path>], seen: &mut BitVec) -> Vec<u32> {
+    seen.clear();
+    for &path in paths {
+        for step in gfa.get_path_steps(&gfa.paths[path]) {
+            seen.set(step.segment().index(), true);
+        }
+    }
+    seen.iter()
+        .enumerate()
+        .filter(|(_, bit)| *bit)
+        .map(|(id, _)| id as u32)
+        .collect()
+}
+
+/// Compute the pairwise similarity between sets of paths.
+///
+/// Rows of the (triangular) matrix are computed in parallel.
+pub fn similarity(gfa: &flatgfa::FlatGFA, sets: &[Vec<Id<Path>>]) -> Similarity {
+    let seg_lens: Vec<usize> = gfa.segs.all().iter().map(|s| s.len()).collect();
+    let seg_sets: Vec<Vec<u32>> = sets
+        .par_iter()
+        .map_init(
+            || BitVec::from_elem(gfa.segs.len(), false),
+            |seen, paths| seg_set(gfa, paths, seen),
+        )
+        .collect();
+    let sizes: Vec<usize> = seg_sets
+        .iter()
+        .map(|segs| segs.iter().map(|&s| seg_lens[s as usize]).sum())
+        .collect();
+
+    // For each row, mark the row's segments and then check every later set.
+    let intersections: Vec<usize> = (0..sets.len())
+        .into_par_iter()
+        .map_init(
+            || BitVec::from_elem(gfa.segs.len(), false),
+            |mark, i| {
+                mark.clear();
+                for &seg in &seg_sets[i] {
+                    mark.set(seg as usize, true);
+                }
+                seg_sets[i + 1..]
+                    .iter()
+                    .map(|other| {
+                        other
+                            .iter()
+                            .filter(|&&s| mark[s as usize])
+                            .map(|&s| seg_lens[s as usize])
+                            .sum()
+                    })
+                    .collect::<Vec<usize>>()
+            },
+        )
+        .flatten_iter()
+        .collect();
+
+    Similarity {
+        sizes,
+        intersections,
+    }
+}
+
+impl Similarity {
+    /// The number of sets.
+    pub fn len(&self) -> usize {
+        self.sizes.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.sizes.is_empty()
+    }
+
+    /// The size of the intersection between two sets.
+    pub fn intersection(&self, i: usize, j: usize) -> usize {
+        if i == j {
+            return self.sizes[i];
+        }
+        let (i, j) = (i.min(j), i.max(j));
+        let n = self.len();
+        self.intersections[n * i - i * (i + 1) / 2 + (j - i - 1)]
+    }
+
+    /// The Jaccard similarity between two sets: the intersection over the union.
+    pub fn jaccard(&self, i: usize, j: usize) -> f64 {
+        let inter = self.intersection(i, j);
+        let union = self.sizes[i] + self.sizes[j] - inter;
+        if union == 0 {
+            0.0
+        } else {
+            inter as f64 / union as f64
+        }
+    }
+
+    /// The containment of set `i` in set `j`: the fraction of `i` that is also in `j`.
+    pub fn containment(&self, i: usize, j: usize) -> f64 {
+        if self.sizes[i] == 0 {
+            0.0
+        } else {
+            self.intersection(i, j) as f64 / self.sizes[i] as f64
+        }
+    }
+
+    /// The Jaccard *distances* (one minus the similarity) in condensed order.
+    ///
+    /// This is the format that SciPy's `linkage` expects for clustering.
+    pub fn condensed_distances(&self) -> Vec<f64> {
+        let n = self.len();
+        (0..n)
+            .flat_map(|i| (i + 1..n).map(move |j| (i, j)))
+            .map(|(i, j)| 1.0 - self.jaccard(i, j))
+            .collect()
+    }
+}
+
+/// A printable table of similarities between every pair of sets.
+pub struct SimilarityTable {
+    pub names: Vec<BString>,
+    pub sim: Similarity,
+}
+
+impl Emit for SimilarityTable {
+    fn emit(self, f: &mut impl Write) -> std::io::Result<()> {
+        writeln!(
+            f,
+            "#group.a\tgroup.b\tgroup.a.length\tgroup.b.length\tintersection\tjaccard\tcontainment.a\tcontainment.b"
+        )?;
+        let n = self.sim.len();
+        for i in 0..n {
+            for j in i + 1..n {
+                writeln!(
+                    f,
+                    "{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}",
+                    self.names[i],
+                    self.names[j],
+                    self.sim.sizes[i],
+                    self.sim.sizes[j],
+                    self.sim.intersection(i, j),
+                    format_float(self.sim.jaccard(i, j), 4),
+                    format_float(self.sim.containment(i, j), 4),
+                    format_float(self.sim.containment(j, i), 4),
+                )?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// A printable condensed distance matrix.
+///
+/// The first line lists the names, and then there is one Jaccard distance per
+/// line, in SciPy's condensed order.
+pub struct CondensedTable {
+    pub names: Vec<BString>,
+    pub sim: Similarity,
+}
+
+impl Emit for CondensedTable {
+    fn emit(self, f: &mut impl Write) -> std::io::Result<()> {
+        write!(f, "#")?;
+        for (i, name) in self.names.iter().enumerate() {
+            if i > 0 {
+                write!(f, "\t")?;
+            }
+            write!(f, "{name}")?;
+        }
+        writeln!(f)?;
+        for dist in self.sim.condensed_distances() {
+            writeln!(f, "{}", format_float(dist, 6))?;
+        }
+        Ok(())
+    }
+}
+
+#[test]
+fn test_similarity() {
+    let gfa_text = b"S\t1\tAAAA\nS\t2\tCC\nS\t3\tGG\n\
+        P\tx\t1+,2+\t*\nP\ty\t1+,3+\t*\nP\tz\t1+,2+,1-\t*\n";
+    let store = crate::parse::Parser::for_heap().parse_mem(gfa_text);
+    let gfa = store.as_ref();
+    let sets: Vec<_> = gfa.paths.ids().map(|id| vec![id]).collect();
+    let sim = similarity(&gfa, &sets);
+    assert_eq!(sim.sizes, vec![6, 6, 6]);
+    assert_eq!(sim.intersections, vec![4, 6, 4]);
+    assert_eq!(sim.jaccard(0, 1), 0.5);
+    assert_eq!(sim.jaccard(0, 2), 1.0);
+    assert_eq!(sim.containment(1, 2), 4.0 / 6.0);
+    assert_eq!(sim.condensed_distances(), vec![0.5, 0.0, 0.5]);
+}
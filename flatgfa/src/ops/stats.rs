@@ -0,0 +1,328 @@
+use crate::emit::{write_json_str, Emit};
+use crate::flatgfa::{self, Orientation};
+use crate::ops::bubble::HandleGraph;
+use crate::ops::component::components;
+use crate::ops::depth::format_float;
+use crate::ops::position::path_len;
+use crate::pansn::{GroupBy, PathGroups};
+use bstr::BString;
+use std::io::Write;
+
+/// A summary of the distribution of some lengths.
+#[derive(Debug, Default, PartialEq)]
+pub struct LengthDist {
+    pub count: usize,
+    pub total: usize,
+    pub min: usize,
+    pub median: f64,
+    pub mean: f64,
+    pub max: usize,
+
+    /// The length such that the items at least this long make up half the total.
+    pub n50: usize,
+
+    /// The number of items at least `n50` long.
+    pub l50: usize,
+}
+
+impl LengthDist {
+    pub fn new(mut lens: Vec<usize>) -> Self {
+        if lens.is_empty() {
+            return Self::default();
+        }
+        lens.sort_unstable();
+        let count = lens.len();
+        let total: usize = lens.iter().sum();
+        let median = if count % 2 == 1 {
+            lens[count / 2] as f64
+        } else {
+            (lens[count / 2 - 1] + lens[count / 2]) as f64 / 2.0
+        };
+
+        // Add up the longest items until we reach half the total.
+        let mut sum = 0;
+        let mut l50 = 0;
+        let mut n50 = 0;
+        for &len in lens.iter().rev() {
+            sum += len;
+            l50 += 1;
+            n50 = len;
+            if 2 * sum >= total {
+                break;
+            }
+        }
+
+        Self {
+            count,
+            total,
+            min: lens[0],
+            median,
+            mean: total as f64 / count as f64,
+            max: lens[count - 1],
+            n50,
+            l50,
+        }
+    }
+}
+
+/// Count the lengths that fall into each power-of-ten bin: 0, 1-9, 10-99, and
+/// so on. Produces `(min, max, count)` triples for every bin up to the largest
+/// length.
+pub fn length_histogram(lens: &[usize]) -> Vec<(usize, usize, usize)> {
+    let Some(&longest) = lens.iter().max() else {
+        return Vec::new();
+    };
+    let bin = |len: usize| {
+        if len == 0 {
+            0
+        } else {
+            len.ilog10() as usize + 1
+        }
+    };
+    let mut counts = vec![0; bin(longest) + 1];
+    for &len in lens {
+        counts[bin(len)] += 1;
+    }
+    counts
+        .into_iter()
+        .enumerate()
+        .map(|(i, count)| match i {
+            0 => (0, 0, count),
+            _ => (10usize.pow(i as u32 - 1), 10usize.pow(i as u32) - 1, count),
+        })
+        .collect()
+}
+
+/// The total sequence in the paths of one sample.
+pub struct SampleStats {
+    pub name: BString,
+    pub paths: usize,
+    pub length: usize,
+}
+
+/// A summary of a graph's segments, links, paths, and samples, like `odgi stats`
+/// or `gfastats`.
+pub struct Stats {
+    pub segs: LengthDist,
+    pub seg_histogram: Vec<(usize, usize, usize)>,
+
+    /// The number of links in each orientation: `++`, `+-`, `-+`, and `--`.
+    pub link_orients: [usize; 4],
+    pub self_loops: usize,
+
+    /// Segment ends that no link touches.
+    pub tips: usize,
+    pub components: usize,
+
+    pub path_lens: LengthDist,
+    pub path_steps: LengthDist,
+
+    /// Path lengths, grouped by sample (from PanSN path names).
+    pub samples: Vec<SampleStats>,
+}
+
+const ORIENTS: [&str; 4] = ["++", "+-", "-+", "--"];
+
+impl Stats {
+    pub fn new(gfa: &flatgfa::FlatGFA) -> Self {
+        let seg_lens: Vec<usize> = gfa.segs.all().iter().map(|s| s.len()).collect();
+
+        let mut link_orients = [0; 4];
+        let mut self_loops = 0;
+        for link in gfa.links.all() {
+            let bwd = |o| (o == Orientation::Backward) as usize;
+            link_orients[2 * bwd(link.from.orient()) + bwd(link.to.orient())] += 1;
+            if link.from.segment() == link.to.segment() {
+                self_loops += 1;
+            }
+        }
+
+        let graph = HandleGraph::new(gfa);
+        let tips = gfa
+            .segs
+            .ids()
+            .flat_map(|id| [Orientation::Forward, Orientation::Backward].map(|o| id.handle(o)))
+            .filter(|&h| graph.succs(h).is_empty())
+            .count();
+
+        let path_lens: Vec<usize> = gfa.paths.all().iter().map(|p| path_len(gfa, p)).collect();
+        let groups = PathGroups::new(gfa, GroupBy::Sample);
+        let samples = groups
+            .names
+            .iter()
+            .zip(&groups.paths)
+            .map(|(name, paths)| SampleStats {
+                name: name.clone(),
+                paths: paths.len(),
+                length: paths.iter().map(|p| path_lens[p.index()]).sum(),
+            })
+            .collect();
+
+        Self {
+            seg_histogram: length_histogram(&seg_lens),
+            segs: LengthDist::new(seg_lens),
+            link_orients,
+            self_loops,
+            tips,
+            components: components(gfa).len(),
+            path_lens: LengthDist::new(path_lens),
+            path_steps: LengthDist::new(gfa.paths.all().iter().map(|p| p.step_count()).collect()),
+            samples,
+        }
+    }
+}
+
+/// Write one line of the text summary.
+fn line(f: &mut impl Write, label: &str, value: impl std::fmt::Display) -> std::io::Result<()> {
+    writeln!(f, "{:<24}{}", format!("{label}:"), value)
+}
+
+impl Emit for Stats {
+    fn emit(self, f: &mut impl Write) -> std::io::Result<()> {
+        let segs = &self.segs;
+        line(f, "Segments", segs.count)?;
+        line(f, "Total segment length", segs.total)?;
+        line(f, "Mean segment length", format_float(segs.mean, 2))?;
+        line(f, "Median segment length", format_float(segs.median, 2))?;
+        line(f, "Min segment length", segs.min)?;
+        line(f, "Max segment length", segs.max)?;
+        line(f, "Segment N50", segs.n50)?;
+        line(f, "Segment L50", segs.l50)?;
+
+        line(f, "Links", self.link_orients.iter().sum::<usize>())?;
+        for (orient, count) in ORIENTS.iter().zip(self.link_orients) {
+            line(f, &format!("Links {orient}"), count)?;
+        }
+        line(f, "Self-loops", self.self_loops)?;
+        line(f, "Tips", self.tips)?;
+        line(f, "Components", self.components)?;
+
+        let paths = &self.path_lens;
+        line(f, "Paths", paths.count)?;
+        line(f, "Total path length", paths.total)?;
+        line(f, "Mean path length", format_float(paths.mean, 2))?;
+        line(f, "Min path length", paths.min)?;
+        line(f, "Max path length", paths.max)?;
+        line(f, "Path N50", paths.n50)?;
+        line(f, "Steps", self.path_steps.total)?;
+        line(
+            f,
+            "Mean steps per path",
+            format_float(self.path_steps.mean, 2),
+        )?;
+
+        writeln!(f, "\nSegment length histogram:")?;
+        for (min, max, count) in &self.seg_histogram {
+            let bin = if min == max {
+                min.to_string()
+            } else {
+                format!("{min}-{max}")
+            };
+            writeln!(f, "  {bin:<22}{count}")?;
+        }
+
+        writeln!(f, "\nSamples (paths, length):")?;
+        for sample in &self.samples {
+            writeln!(f, "  {}\t{}\t{}", sample.name, sample.paths, sample.length)?;
+        }
+        Ok(())
+    }
+}
+
+/// Write a length distribution as a JSON object.
+fn write_dist_json(f: &mut impl Write, dist: &LengthDist) -> std::io::Result<()> {
+    write!(
+        f,
+        "{{\"count\": {}, \"total\": {}, \"min\": {}, \"median\": {}, \"mean\": {}, \
+         \"max\": {}, \"n50\": {}, \"l50\": {}}}",
+        dist.count,
+        dist.total,
+        dist.min,
+        format_float(dist.median, 2),
+        format_float(dist.mean, 2),
+        dist.max,
+        dist.n50,
+        dist.l50
+    )
+}
+
+/// The same summary, as a JSON document.
+pub struct StatsJson(pub Stats);
+
+impl Emit for StatsJson {
+    fn emit(self, f: &mut impl Write) -> std::io::Result<()> {
+        let stats = self.0;
+        write!(f, "{{\n  \"segments\": ")?;
+        write_dist_json(f, &stats.segs)?;
+        write!(f, ",\n  \"segment_histogram\": [")?;
+        for (i, (min, max, count)) in stats.seg_histogram.iter().enumerate() {
+            let sep = if i > 0 { ", " } else { "" };
+            write!(f, "{sep}[{min}, {max}, {count}]")?;
+        }
+
+        write!(f, "],\n  \"links\": {{")?;
+        let total: usize = stats.link_orients.iter().sum();
+        write!(f, "\"count\": {total}")?;
+        for (orient, count) in ORIENTS.iter().zip(stats.link_orients) {
+            write!(f, ", \"{orient}\": {count}")?;
+        }
+        writeln!(f, "}},")?;
+        writeln!(f, "  \"self_loops\": {},", stats.self_loops)?;
+        writeln!(f, "  \"tips\": {},", stats.tips)?;
+        writeln!(f, "  \"components\": {},", stats.components)?;
+
+        write!(f, "  \"paths\": ")?;
+        write_dist_json(f, &stats.path_lens)?;
+        write!(f, ",\n  \"steps\": ")?;
+        write_dist_json(f, &stats.path_steps)?;
+
+        writeln!(f, ",\n  \"samples\": [")?;
+        for (i, sample) in stats.samples.iter().enumerate() {
+            write!(f, "    {{\"name\": ")?;
+            write_json_str(f, &sample.name)?;
+            let sep = if i + 1 < stats.samples.len() { "," } else { "" };
+            writeln!(
+                f,
+                ", \"paths\": {}, \"length\": {}}}{sep}",
+                sample.paths, sample.length
+            )?;
+        }
+        writeln!(f, "  ]\n}}")
+    }
+}
+
+#[test]
+fn test_length_dist() {
+    let dist = LengthDist::new(vec![6, 4]);
+    assert_eq!((dist.min, dist.median, dist.max), (4, 5.0, 6));
+
+    let dist = LengthDist::new(vec![10, 1, 2, 5]);
+    assert_eq!((dist.total, dist.n50, dist.l50), (18, 10, 1));
+    assert_eq!(LengthDist::new(Vec::new()).count, 0);
+}
+
+#[test]
+fn test_stats() {
+    let gfa_text = b"S\t1\tAAAAAAAAAA\nS\t2\tC\nS\t3\tGG\nS\t4\tTTTTT\n\
+        L\t1\t+\t2\t+\t0M\nL\t2\t+\t3\t-\t0M\nL\t3\t+\t3\t+\t0M\n\
+        P\ta#1#chr\t1+,2+,3-\t*\nP\ta#2#chr\t1+\t*\nP\tb#1#chr\t4+\t*\n";
+    let store = crate::parse::Parser::for_heap().parse_mem(gfa_text);
+    let gfa = store.as_ref();
+
+    let stats = Stats::new(&gfa);
+    assert_eq!(
+        (stats.segs.total, stats.segs.n50, stats.segs.l50),
+        (18, 10, 1)
+    );
+    assert_eq!(stats.link_orients, [2, 1, 0, 0]);
+    assert_eq!(stats.self_loops, 1);
+    assert_eq!(stats.components, 2);
+
+    // Segment 4 has two tips, 1 has one, and 3 has none (because of its loop).
+    assert_eq!(stats.tips, 3);
+
+    assert_eq!(stats.path_steps.total, 5);
+    let samples: Vec<_> = stats.samples.iter().map(|s| (s.paths, s.length)).collect();
+    assert_eq!(samples, vec![(2, 23), (1, 5)]);
+    assert_eq!(stats.seg_histogram, vec![(0, 0, 0), (1, 9, 3), (10, 99, 1)]);
+}
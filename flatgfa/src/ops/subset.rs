@@ -0,0 +1,132 @@
+use crate::flatgfa::{self, AlignOp, Path, Segment};
+use crate::pool::{Id, Store};
+
+/// Find the paths whose names satisfy a predicate, in graph order.
+pub fn select_paths(gfa: &flatgfa::FlatGFA, pred: impl Fn(&bstr::BStr) -> bool) -> Vec<Id<Path>> {
+    gfa.paths
+        .items()
+        .filter(|(_, path)| pred(gfa.get_path_name(path)))
+        .map(|(id, _)| id)
+        .collect()
+}
+
+/// Create a new graph that contains only some of the paths in an old graph.
+///
+/// By default, all the segments and links are kept as they are. With `compact`,
+/// we drop the segments that none of the kept paths traverse, drop the links
+/// that are incident to any dropped segment, and renumber the remaining
+/// segments with sequential names starting at 1 (preserving their order).
+pub fn subset_paths(
+    gfa: &flatgfa::FlatGFA,
+    paths: &[Id<Path>],
+    compact: bool,
+) -> flatgfa::HeapGFAStore {
+    let mut store = flatgfa::HeapGFAStore::default();
+    if !gfa.header.is_empty() {
+        store.add_header(gfa.header.all());
+    }
+
+    // Decide which segments to keep.
+    let mut keep = vec![!compact; gfa.segs.len()];
+    if compact {
+        for &path_id in paths {
+            for step in gfa.get_path_steps(&gfa.paths[path_id]) {
+                keep[step.segment().index()] = true;
+            }
+        }
+    }
+
+    // Add the segments, recording where each one went.
+    let mut seg_map: Vec<Option<Id<Segment>>> = vec![None; gfa.segs.len()];
+    for (id, seg) in gfa.segs.items() {
+        if keep[id.index()] {
+            let name = if compact {
+                store.segs.len() + 1
+            } else {
+                seg.name
+            };
+            let new_id = store.add_seg(name, gfa.get_seq(seg), gfa.get_optional_data(seg));
+            seg_map[id.index()] = Some(new_id);
+        }
+    }
+    let tr_handle = |handle: flatgfa::Handle| {
+        seg_map[handle.segment().index()].map(|id| id.handle(handle.orient()))
+    };
+
+    // Add the selected paths.
+    for &path_id in paths {
+        let path = &gfa.paths[path_id];
+        let steps = store.add_steps(
+            gfa.get_path_steps(path)
+                .map(|step| tr_handle(*step).expect("path step in dropped segment")),
+        );
+        let overlaps: Vec<Vec<AlignOp>> = gfa.overlaps[path.overlaps]
+            .iter()
+            .map(|span| gfa.get_alignment(*span).ops.to_vec())
+            .collect();
+        store.add_path(gfa.get_path_name(path), steps, overlaps.into_iter());
+    }
+
+    // Add the links between remaining segments.
+    for link in gfa.links.all() {
+        if let (Some(from), Some(to)) = (tr_handle(link.from), tr_handle(link.to)) {
+            store.add_link(from, to, gfa.get_alignment(link.overlap).ops.to_vec());
+        }
+    }
+
+    store
+}
+
+#[test]
+fn test_subset_paths() {
+    let gfa_text = b"S\t1\tA\nS\t2\tC\nS\t3\tG\nS\t4\tT\n\
+        L\t1\t+\t3\t+\t0M\nL\t1\t+\t2\t+\t0M\nL\t2\t+\t4\t+\t0M\n\
+        P\tHG1#chr1\t1+,3-\t*\nP\tHG2#chr1\t1+,2+,4+\t*\nP\tHG1#chr2\t3+\t*\n";
+    let store = crate::parse::Parser::for_heap().parse_mem(gfa_text);
+    let gfa = store.as_ref();
+    let names = |gfa: &flatgfa::FlatGFA, paths: &[Id<Path>]| -> Vec<String> {
+        paths
+            .iter()
+            .map(|&p| gfa.get_path_name(&gfa.paths[p]).to_string())
+            .collect()
+    };
+
+    // Select by prefix and by regex, in graph order.
+    let hg1 = select_paths(&gfa, |name| name.starts_with(b"HG1#"));
+    assert_eq!(names(&gfa, &hg1), vec!["HG1#chr1", "HG1#chr2"]);
+    let re = regex::bytes::Regex::new("#chr1$").unwrap();
+    let chr1 = select_paths(&gfa, |name| re.is_match(name));
+    assert_eq!(names(&gfa, &chr1), vec!["HG1#chr1", "HG2#chr1"]);
+
+    // Without compaction, everything but the paths stays.
+    let sub = subset_paths(&gfa, &hg1, false);
+    let sub = sub.as_ref();
+    assert_eq!(
+        (sub.segs.len(), sub.links.len(), sub.paths.len()),
+        (4, 3, 2)
+    );
+
+    // Compaction keeps segments 1 and 3, renamed 1 and 2, and the link between them.
+    let sub = subset_paths(&gfa, &hg1, true);
+    let sub = sub.as_ref();
+    let segs: Vec<_> = sub
+        .segs
+        .all()
+        .iter()
+        .map(|s| (s.name, sub.get_seq(s).to_string()))
+        .collect();
+    assert_eq!(segs, vec![(1, "A".to_string()), (2, "G".to_string())]);
+    assert_eq!(sub.links.len(), 1);
+    let path = &sub.paths[sub.find_path("HG1#chr1".into()).unwrap()];
+    let steps: Vec<_> = sub
+        .get_path_steps(path)
+        .map(|h| (sub.get_handle_seg(*h).name, h.orient()))
+        .collect();
+    assert_eq!(
+        steps,
+        vec![
+            (1, flatgfa::Orientation::Forward),
+            (2, flatgfa::Orientation::Backward)
+        ]
+    );
+}
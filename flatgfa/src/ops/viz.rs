@@ -0,0 +1,227 @@
+use crate::emit::Emit;
+use crate::flatgfa::{self, Path};
+use crate::ops::bin::{self, Bin};
+use crate::pool::Id;
+use std::io::Write;
+use std::ops::Range;
+use std::str::FromStr;
+
+/// An RGB color.
+pub type Rgb = [u8; 3];
+
+const BACKGROUND: Rgb = [255, 255, 255];
+
+/// Colors for coverage 1, 2, 3, ..., and then everything higher than that.
+/// (This is ColorBrewer's 9-class "Blues" scheme, minus the lightest shade.)
+const COVERAGE_COLORS: [Rgb; 8] = [
+    [222, 235, 247],
+    [198, 219, 239],
+    [158, 202, 225],
+    [107, 174, 214],
+    [66, 146, 198],
+    [33, 113, 181],
+    [8, 81, 156],
+    [8, 48, 107],
+];
+
+const FORWARD: Rgb = [0, 0, 0];
+const REVERSE: Rgb = [228, 26, 28];
+
+/// What determines the color of each pixel in a path's row.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum ColorBy {
+    /// How many times the path covers that part of the pangenome.
+    Coverage,
+    /// Whether the path traverses that part of the pangenome forward (black)
+    /// or in reverse (red), or a mix of the two.
+    Orientation,
+}
+
+impl FromStr for ColorBy {
+    type Err = &'static str;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "coverage" => Ok(Self::Coverage),
+            "orientation" => Ok(Self::Orientation),
+            _ => Err("color must be coverage or orientation"),
+        }
+    }
+}
+
+impl ColorBy {
+    fn color(self, bin: &Bin) -> Rgb {
+        match self {
+            Self::Coverage => {
+                let idx = (bin.coverage.ceil() as usize).clamp(1, COVERAGE_COLORS.len());
+                COVERAGE_COLORS[idx - 1]
+            }
+            Self::Orientation => {
+                let mix =
+                    |f: u8, r: u8| (f as f64 + (r as f64 - f as f64) * bin.inversion).round() as u8;
+                [
+                    mix(FORWARD[0], REVERSE[0]),
+                    mix(FORWARD[1], REVERSE[1]),
+                    mix(FORWARD[2], REVERSE[2]),
+                ]
+            }
+        }
+    }
+}
+
+/// A linearized, 1D layout of some paths, like `odgi viz`.
+///
+/// The x axis is the pangenome: all the segments, concatenated in order. Each
+/// path gets a row, and each column is a pixel-wide bin of the pangenome. A
+/// column is empty where the path doesn't touch that bin.
+pub struct Layout<'a> {
+    pub gfa: &'a flatgfa::FlatGFA<'a>,
+
+    /// The colors for each path's row.
+    pub rows: Vec<(Id<Path>, Vec<Option<Rgb>>)>,
+
+    /// The number of pixels (columns) in each row.
+    pub width: usize,
+
+    /// The height of each row in pixels. There is a 1-pixel gap between rows.
+    pub row_height: usize,
+}
+
+/// Lay out some paths in an image that is at most `width` pixels wide.
+///
+/// The layout covers a `range` of the pangenome, or the whole thing if `None`.
+/// Each pixel covers the same number of base pairs, so the image can be
+/// narrower than `width` when the range is short.
+pub fn layout<'a>(
+    gfa: &'a flatgfa::FlatGFA<'a>,
+    paths: &[Id<Path>],
+    range: Option<Range<usize>>,
+    width: usize,
+    row_height: usize,
+    color: ColorBy,
+) -> Layout<'a> {
+    let len = *bin::seg_offsets(gfa).last().unwrap();
+    let range = range.unwrap_or(0..len);
+    let range = range.start..range.end.min(len);
+    let bp_per_px = range.len().div_ceil(width).max(1);
+    let width = range.len().div_ceil(bp_per_px);
+
+    let bins = bin::bin_range(gfa, paths, Some(range), bp_per_px);
+    let rows = bins
+        .paths
+        .into_iter()
+        .map(|(path, path_bins)| {
+            let mut row = vec![None; width];
+            for b in &path_bins {
+                row[b.bin] = Some(color.color(b));
+            }
+            (path, row)
+        })
+        .collect();
+    Layout {
+        gfa,
+        rows,
+        width,
+        row_height,
+    }
+}
+
+impl Layout<'_> {
+    /// The total height of the image, in pixels.
+    pub fn height(&self) -> usize {
+        (self.row_height + 1) * self.rows.len().max(1) - 1
+    }
+
+    /// Render the layout to a PNG image.
+    pub fn write_png(&self, f: impl Write) -> Result<(), png::EncodingError> {
+        let mut encoder = png::Encoder::new(f, self.width as u32, self.height() as u32);
+        encoder.set_color(png::ColorType::Rgb);
+        encoder.set_depth(png::BitDepth::Eight);
+        let mut writer = encoder.write_header()?;
+
+        let mut data = Vec::with_capacity(self.width * self.height() * 3);
+        for (i, (_, row)) in self.rows.iter().enumerate() {
+            if i > 0 {
+                data.extend(std::iter::repeat_n(BACKGROUND, self.width).flatten());
+            }
+            let line: Vec<u8> = row.iter().flat_map(|c| c.unwrap_or(BACKGROUND)).collect();
+            for _ in 0..self.row_height {
+                data.extend_from_slice(&line);
+            }
+        }
+        data.resize(self.width * self.height() * 3, BACKGROUND[0]);
+        writer.write_image_data(&data)
+    }
+}
+
+/// A layout rendered as an SVG image.
+///
+/// Runs of pixels with the same color become a single rectangle. Each row has
+/// a `<title>` with the path's name, which most viewers show on hover.
+pub struct Svg<'a>(pub Layout<'a>);
+
+impl Emit for Svg<'_> {
+    fn emit(self, f: &mut impl Write) -> std::io::Result<()> {
+        let layout = self.0;
+        writeln!(
+            f,
+            "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{}\" height=\"{}\" shape-rendering=\"crispEdges\">",
+            layout.width,
+            layout.height()
+        )?;
+        writeln!(f, "<rect width=\"100%\" height=\"100%\" fill=\"#ffffff\"/>")?;
+        for (i, (path, row)) in layout.rows.iter().enumerate() {
+            let y = i * (layout.row_height + 1);
+            let name = layout.gfa.get_path_name(&layout.gfa.paths[*path]);
+            writeln!(f, "<g><title>{}</title>", xml_escape(&name.to_string()))?;
+            let mut x = 0;
+            for run in row.chunk_by(|a, b| a == b) {
+                if let Some([r, g, b]) = run[0] {
+                    writeln!(
+                        f,
+                        "<rect x=\"{}\" y=\"{}\" width=\"{}\" height=\"{}\" fill=\"#{:02x}{:02x}{:02x}\"/>",
+                        x,
+                        y,
+                        run.len(),
+                        layout.row_height,
+                        r,
+                        g,
+                        b
+                    )?;
+                }
+                x += run.len();
+            }
+            writeln!(f, "</g>")?;
+        }
+        writeln!(f, "</svg>")
+    }
+}
+
+fn xml_escape(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
+
+#[test]
+fn test_layout() {
+    // The pangenome is AAAA|CC|GGGG. Path y skips segment 2 and reverses segment 3.
+    let gfa_text = b"S\t1\tAAAA\nS\t2\tCC\nS\t3\tGGGG\n\
+        P\tx\t1+,2+,3+\t*\nP\ty\t1+,1+,3-\t*\n";
+    let store = crate::parse::Parser::for_heap().parse_mem(gfa_text);
+    let gfa = store.as_ref();
+    let paths: Vec<_> = gfa.paths.ids().collect();
+
+    let viz = layout(&gfa, &paths, None, 5, 2, ColorBy::Coverage);
+    assert_eq!(viz.width, 5);
+    assert_eq!(viz.height(), 5);
+    let y = &viz.rows[1].1;
+    assert_eq!(y[0], Some(COVERAGE_COLORS[1]));
+    assert_eq!(y[2], None);
+    assert_eq!(y[4], Some(COVERAGE_COLORS[0]));
+
+    let viz = layout(&gfa, &paths, Some(6..10), 100, 1, ColorBy::Orientation);
+    assert_eq!(viz.width, 4);
+    assert_eq!(viz.rows[0].1, vec![Some(FORWARD); 4]);
+    assert_eq!(viz.rows[1].1, vec![Some(REVERSE); 4]);
+}
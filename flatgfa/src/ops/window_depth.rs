@@ -7,9 +7,10 @@ use crate::pool::Store;
 use crate::FlatGFA;
 use bstr::BStr;
 
-struct SegmentDepth {
-    depth: f64,
-    range: (usize, usize),
+/// A weight attached to a range of base-pair offsets.
+pub(crate) struct SegmentDepth {
+    pub depth: f64,
+    pub range: (usize, usize),
 }
 
 /// A sequence of equally-sized windows along a certain path.
@@ -26,11 +27,8 @@ pub struct Windows<'a> {
 
 impl<'a> Emit for Windows<'a> {
     fn emit(self, f: &mut impl std::io::Write) -> std::io::Result<()> {
-        let mut pos = self.start;
-        while pos < self.end {
-            let end = (pos + self.size).min(self.end);
-            writeln!(f, "{}\t{}\t{}", self.name, pos, end)?;
-            pos = end;
+        for (start, end) in self.ranges() {
+            writeln!(f, "{}\t{}\t{}", self.name, start, end)?;
         }
         Ok(())
     }
@@ -41,11 +39,8 @@ impl<'a> Windows<'a> {
         let name = store.name_data.add_slice(self.name.as_ref());
         store.entries.reserve(self.len());
 
-        let mut start = self.start;
-        while start < self.end {
-            let end = (start + self.size).min(self.end);
+        for (start, end) in self.ranges() {
             store.entries.add(BEDEntry { name, start, end });
-            start = end;
         }
     }
 
@@ -55,6 +50,14 @@ impl<'a> Windows<'a> {
         store
     }
 
+    /// The start and end coordinates of each window.
+    pub fn ranges(&self) -> impl Iterator<Item = (u64, u64)> {
+        let (size, end) = (self.size, self.end);
+        (self.start..self.end)
+            .step_by(size as usize)
+            .map(move |start| (start, (start + size).min(end)))
+    }
+
     /// The number of windows in the sequence.
     pub fn len(&self) -> usize {
         (self.end - self.start).div_ceil(self.size) as usize
@@ -111,9 +114,13 @@ fn overlap(a: (usize, usize), b: (usize, usize)) -> (usize, usize) {
 /// Compute the per-window weighted depth.
 ///
 /// Given weighted segment depths from `weighted_depths`, assign that weight to
-/// each of the base-pair ranges in `windows`.
+/// each of the base-pair ranges in `windows`. Both the segments and the windows
+/// must be sorted by offset.
 #[allow(clippy::mut_range_bound)]
-fn assign_depths(seg_depth: impl IntoIterator<Item = SegmentDepth>, windows: &FlatBED) -> Vec<f64> {
+pub(crate) fn assign_depths(
+    seg_depth: impl IntoIterator<Item = SegmentDepth>,
+    windows: &FlatBED,
+) -> Vec<f64> {
     let mut depths: Vec<f64> = vec![0.0; windows.get_num_entries()];
 
     // Walk down the segments in the path.
@@ -173,9 +180,13 @@ impl Emit for IntervalDepth<'_> {
 /// Return one weighted depth value per interval. We require a path so we can
 /// avoid computing any average weights for segments not included within that
 /// path.
-fn interval_depth(gfa: &flatgfa::FlatGFA, path: Id<Path>, intervals: &FlatBED) -> Vec<f64> {
-    let depth = seg_depth(gfa);
-    let seg_depths = weighted_depths(gfa, &depth, path);
+fn interval_depth(
+    gfa: &flatgfa::FlatGFA,
+    depth: &[usize],
+    path: Id<Path>,
+    intervals: &FlatBED,
+) -> Vec<f64> {
+    let seg_depths = weighted_depths(gfa, depth, path);
     assign_depths(seg_depths, intervals)
 }
 
@@ -184,6 +195,19 @@ pub fn window_depth(
     gfa: &flatgfa::FlatGFA,
     path: Id<Path>,
     window_size: usize,
+) -> (HeapBEDStore, Vec<f64>) {
+    window_depth_with(gfa, &seg_depth(gfa), path, window_size)
+}
+
+/// Compute window depths along a path using precomputed segment depths.
+///
+/// The segment depths can be something other than ordinary depth: for
+/// example, `group_seg_depth` counts samples instead of paths.
+pub fn window_depth_with(
+    gfa: &flatgfa::FlatGFA,
+    depth: &[usize],
+    path: Id<Path>,
+    window_size: usize,
 ) -> (HeapBEDStore, Vec<f64>) {
     let windows = Windows {
         name: gfa.get_path_name(&gfa.paths[path]),
@@ -192,7 +216,7 @@ pub fn window_depth(
         size: window_size as u64,
     }
     .as_bed();
-    let depths = interval_depth(gfa, path, &windows.as_ref());
+    let depths = interval_depth(gfa, depth, path, &windows.as_ref());
     (windows, depths)
 }
 
@@ -207,5 +231,5 @@ pub fn bed_depth(gfa: &flatgfa::FlatGFA, intervals: &FlatBED) -> Vec<f64> {
     let path_name = intervals.get_name_of_entry(&intervals.entries.all()[0]);
     let path = gfa.find_path(path_name).expect("path not found in graph");
 
-    interval_depth(gfa, path, intervals)
+    interval_depth(gfa, &seg_depth(gfa), path, intervals)
 }
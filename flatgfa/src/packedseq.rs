@@ -450,7 +450,7 @@ mod tests {
 
     #[test]
     fn test_display_single() {
-        let vec = PackedSeqStore::from_slice(&[Nucleotide::T.into()]);
+        let vec = PackedSeqStore::from_slice(&[Nucleotide::T]);
         assert_eq!("T", vec.as_ref().to_string());
     }
 
@@ -0,0 +1,151 @@
+use crate::flatgfa::{FlatGFA, Path};
+use crate::pool::Id;
+use bstr::{BStr, BString, ByteSlice};
+use std::collections::HashMap;
+use std::str::FromStr;
+
+/// The separator between fields in PanSN names.
+pub const DELIM: u8 = b'#';
+
+/// A path name in the Pangenome Sequence Naming (PanSN) convention.
+///
+/// PanSN names look like `sample#haplotype#contig`, as in `HG002#1#chr20`. See
+/// the [spec](https://github.com/pangenome/PanSN-spec). We also accept names
+/// with only two fields, `sample#contig`, which have no haplotype. Any further
+/// `#`s belong to the contig name.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct PanSN<'a> {
+    pub sample: &'a BStr,
+    pub haplotype: Option<&'a BStr>,
+    pub contig: &'a BStr,
+}
+
+impl<'a> PanSN<'a> {
+    /// Parse a path name. Returns `None` if the name has no `#` delimiters.
+    pub fn parse(name: &'a BStr) -> Option<Self> {
+        let (sample, rest) = name.split_once_str(&[DELIM])?;
+        let (haplotype, contig) = match rest.split_once_str(&[DELIM]) {
+            Some((hap, contig)) => (Some(hap.as_bstr()), contig),
+            None => (None, rest),
+        };
+        Some(Self {
+            sample: sample.as_bstr(),
+            haplotype,
+            contig: contig.as_bstr(),
+        })
+    }
+
+    /// The sample and haplotype together, as in `HG002#1`.
+    pub fn sample_hap(&self) -> BString {
+        let mut key = BString::from(self.sample);
+        if let Some(hap) = self.haplotype {
+            key.push(DELIM);
+            key.extend_from_slice(hap);
+        }
+        key
+    }
+}
+
+/// A way to group paths by the parts of their PanSN names.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum GroupBy {
+    /// Group by sample name.
+    Sample,
+    /// Group by sample and haplotype.
+    Haplotype,
+    /// Group by contig name (across all samples).
+    Contig,
+}
+
+impl FromStr for GroupBy {
+    type Err = &'static str;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "sample" => Ok(Self::Sample),
+            "haplotype" => Ok(Self::Haplotype),
+            "contig" => Ok(Self::Contig),
+            _ => Err("grouping must be sample, haplotype, or contig"),
+        }
+    }
+}
+
+impl GroupBy {
+    /// Get the group name for a path name.
+    ///
+    /// Names that don't follow PanSN are a group of their own.
+    pub fn key(self, name: &BStr) -> BString {
+        match PanSN::parse(name) {
+            None => name.into(),
+            Some(pansn) => match self {
+                Self::Sample => pansn.sample.into(),
+                Self::Haplotype => pansn.sample_hap(),
+                Self::Contig => pansn.contig.into(),
+            },
+        }
+    }
+}
+
+/// Paths grouped by some part of their names.
+pub struct PathGroups {
+    /// The group names, in order of first appearance in the graph.
+    pub names: Vec<BString>,
+
+    /// The paths in each group, in graph order.
+    pub paths: Vec<Vec<Id<Path>>>,
+}
+
+impl PathGroups {
+    /// Group all the paths in a graph.
+    pub fn new(gfa: &FlatGFA, by: GroupBy) -> Self {
+        Self::of_paths(gfa, gfa.paths.ids(), by)
+    }
+
+    /// Group some of the paths in a graph.
+    pub fn of_paths(gfa: &FlatGFA, paths: impl Iterator<Item = Id<Path>>, by: GroupBy) -> Self {
+        let mut index: HashMap<BString, usize> = HashMap::new();
+        let mut groups = Self {
+            names: Vec::new(),
+            paths: Vec::new(),
+        };
+        for id in paths {
+            let key = by.key(gfa.get_path_name(&gfa.paths[id]));
+            let idx = *index.entry(key.clone()).or_insert_with(|| {
+                groups.names.push(key);
+                groups.paths.push(Vec::new());
+                groups.names.len() - 1
+            });
+            groups.paths[idx].push(id);
+        }
+        groups
+    }
+
+    /// The number of groups.
+    pub fn len(&self) -> usize {
+        self.names.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.names.is_empty()
+    }
+}
+
+#[test]
+fn test_parse_pansn() {
+    let name = BStr::new("HG002#1#chr20");
+    let pansn = PanSN::parse(name).unwrap();
+    assert_eq!(pansn.sample, "HG002");
+    assert_eq!(pansn.haplotype, Some(BStr::new("1")));
+    assert_eq!(pansn.contig, "chr20");
+    assert_eq!(pansn.sample_hap(), "HG002#1");
+
+    let pansn = PanSN::parse(BStr::new("HG002#2#chr1#frag")).unwrap();
+    assert_eq!(pansn.contig, "chr1#frag");
+
+    let pansn = PanSN::parse(BStr::new("grch38#chrX")).unwrap();
+    assert_eq!(pansn.haplotype, None);
+    assert_eq!(pansn.contig, "chrX");
+
+    assert_eq!(PanSN::parse(BStr::new("chr1")), None);
+    assert_eq!(GroupBy::Sample.key(BStr::new("chr1")), "chr1");
+}